@@ -278,8 +278,42 @@ enum RecipeCommand {
 #[derive(Subcommand)]
 enum Command {
     /// Authenticate with GitHub (OAuth2 PKCE)
-    #[command(about = "Authenticate with GitHub (OAuth2 PKCE)")]
+    #[command(
+        about = "Authenticate with GitHub (OAuth2 PKCE)",
+        after_long_help = "Exit status: 0 success, 1 other failure, 3 missing configuration, 4 network error, 5 state mismatch, 6 callback timeout, 7 not authenticated, 8 token rejected, 9 authorization denied, 10 device code expired, 11 rate limited, 130 cancelled with Ctrl-C."
+    )]
     Auth {
+        /// Output format for scripts
+        #[arg(
+            long,
+            global = true,
+            value_name = "FORMAT",
+            value_parser = ["text", "json"],
+            help = "Output format: text or json",
+            long_help = "With 'json', status, login and logout print a single JSON object to stdout and send prompts and messages to stderr; failures print {\"error\": \"...\"}. Defaults to GOOSE_OUTPUT, or text."
+        )]
+        output: Option<String>,
+
+        /// Named account to operate on
+        #[arg(
+            long,
+            global = true,
+            value_name = "NAME",
+            help = "Account to use, e.g. work or personal (default: the active account)",
+            long_help = "Keep several logins per provider side by side: tokens are stored under the account name, and status, logout and revoke act on it. Defaults to GOOSE_AUTH_ACCOUNT, then the account chosen with `goose auth switch`, then 'default'."
+        )]
+        account: Option<String>,
+
+        /// JSON file defining a custom provider
+        #[arg(
+            long,
+            global = true,
+            value_name = "PATH",
+            help = "JSON file defining a custom OAuth provider",
+            long_help = "Use the provider described in a JSON file with authorize_url, token_url, userinfo_url and optionally scopes, login_field, uses_pkce and client_auth_method (client_secret_post, client_secret_basic or none). It replaces the default and `custom` providers, and the endpoint overrides do not apply to it. Defaults to GOOSE_AUTH_PROVIDER_CONFIG."
+        )]
+        provider_config: Option<PathBuf>,
+
         #[command(subcommand)]
         command: AuthCommand,
     },
@@ -694,11 +728,247 @@ enum AuthCommand {
         /// Use manual mode (paste the redirected URL)
         #[arg(long, default_value_t = false)]
         manual: bool,
+
+        /// Scopes to request, replacing the defaults
+        #[arg(
+            long,
+            value_name = "SCOPES",
+            help = "Scopes to request (space or comma separated)"
+        )]
+        scopes: Option<String>,
+
+        /// File listing the scopes to request
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Load scopes from a file (one or more per line, '#' comments allowed)",
+            long_help = "Load the scopes to request from a file. Scopes may be separated by newlines, spaces or commas, and '#' starts a comment. Overrides GOOSE_GITHUB_SCOPES_FILE and GOOSE_GITHUB_SCOPES; --scopes takes precedence."
+        )]
+        scopes_file: Option<PathBuf>,
+
+        /// Keep everything in memory for this run
+        #[arg(
+            long,
+            help = "Never write tokens, identity or caches to disk or the keychain for this run",
+            long_help = "Guarantee that this invocation persists nothing: the token is held in memory only and all token, identity and cache writes are skipped, regardless of GOOSE_TOKEN_STORE or other settings. Equivalent to GOOSE_AUTH_NO_PERSIST=1."
+        )]
+        no_persist: bool,
+
+        /// Force the consent screen again
+        #[arg(
+            long,
+            help = "Re-authenticate, e.g. to grant additional scopes",
+            long_help = "Run a fresh login with the consent screen forced. Scopes granted to the stored token are merged with the requested ones so no access is lost; use --scopes-exact to narrow instead."
+        )]
+        reauth: bool,
+
+        /// Do not merge with previously granted scopes
+        #[arg(
+            long,
+            help = "Request exactly the given scopes instead of merging with previously granted ones"
+        )]
+        scopes_exact: bool,
+
+        /// Device flow for CI logs
+        #[arg(
+            long,
+            conflicts_with = "manual",
+            help = "Use the device flow with log-friendly output (no browser, no callback server)",
+            long_help = "Run the device authorization flow for environments without a browser: the code and URL are printed once, followed by periodic 'still waiting' lines suitable for CI logs instead of an interactive prompt. Exits with an error if the code expires."
+        )]
+        headless: bool,
+
+        /// OIDC prompt behavior at the identity provider
+        #[arg(
+            long,
+            value_name = "PROMPT",
+            value_parser = ["none", "login", "consent", "select_account"],
+            help = "Set the authorize 'prompt' parameter: none, login, consent or select_account",
+            long_help = "Pass an OIDC 'prompt' value on the authorize URL: 'consent' forces the consent screen, 'login' forces re-authentication, 'select_account' shows the account picker and 'none' fails instead of showing any UI. Defaults to GOOSE_AUTH_PROMPT; --reauth implies 'consent' when neither is set."
+        )]
+        prompt: Option<String>,
+
+        /// Providers to log into
+        #[arg(
+            long = "provider",
+            value_name = "NAME",
+            help = "Provider to log into (github, gitlab, oidc or custom); repeat to log into several in one run",
+            long_help = "Provider to log into: github (the default), gitlab, oidc for the OpenID Connect issuer at GOOSE_OIDC_ISSUER, or custom, whose endpoints come from GOOSE_AUTH_AUTHORIZE_URL, GOOSE_AUTH_TOKEN_URL and GOOSE_AUTH_IDENTITY_URL. Defaults to GOOSE_AUTH_PROVIDER. Repeat the flag to log into several providers in one run: the flows run one after another, each token is stored under its provider's namespace, and a summary is printed at the end. A failed provider does not stop the others, but the command exits non-zero."
+        )]
+        providers: Vec<String>,
+
+        /// Allow rebinding a port in TIME_WAIT
+        #[arg(
+            long,
+            help = "Set SO_REUSEADDR on the callback listener",
+            long_help = "Set SO_REUSEADDR on the callback listener so back-to-back logins on a pinned port do not fail with 'address already in use' while the previous connection is in TIME_WAIT. On by default for loopback listen addresses; GOOSE_AUTH_REUSE_PORT=0 or 1 overrides the default."
+        )]
+        reuse_port: bool,
+
+        /// Do not mask secrets in debugging output
+        #[arg(
+            long,
+            help = "Show the client secret and code unmasked in GOOSE_AUTH_PRINT_CURL output",
+            long_help = "With GOOSE_AUTH_PRINT_CURL=1, print the client secret, code and verifier in the equivalent curl command instead of masking them. The output can then replay the exchange but must not be shared."
+        )]
+        unsafe_show_secrets: bool,
+
+        /// Fallback when the callback does not arrive in time
+        #[arg(
+            long,
+            value_name = "ACTION",
+            value_parser = ["fail", "manual", "device"],
+            help = "What to do when the callback times out: fail, manual or device",
+            long_help = "What the automatic flow does when the OAuth callback does not arrive in time: 'manual' (the default) asks you to paste the redirected URL, 'fail' exits with a timeout error, which suits CI, and 'device' switches to the device flow with the same options. Defaults to GOOSE_AUTH_TIMEOUT_ACTION."
+        )]
+        timeout_action: Option<String>,
+
+        /// Seconds to wait for the OAuth callback
+        #[arg(
+            long,
+            value_name = "SECS",
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Seconds to wait for the OAuth callback (default 60)",
+            long_help = "How long the automatic flow waits for the OAuth callback before the --timeout-action fallback applies. Raise it when SSO or 2FA takes longer than a minute. Defaults to GOOSE_AUTH_TIMEOUT_SECS, or 60."
+        )]
+        timeout: Option<u64>,
+
+        /// Print URLs instead of opening a browser
+        #[arg(
+            long,
+            help = "Never launch a browser; only print the URL to open",
+            long_help = "Print the authorization URL without launching a browser, e.g. over SSH or when the browser runs on another machine. Without --manual, an interactive terminal goes straight to pasting the redirected URL. Equivalent to GOOSE_NO_BROWSER=1."
+        )]
+        no_browser: bool,
+
+        /// Exchange the code on another machine
+        #[arg(
+            long,
+            help = "For air-gapped hosts: print the token request to run elsewhere",
+            long_help = "Run the manual flow without contacting the token endpoint: goose prints the exchange as a curl command, with the code and verifier filled in and the client secret masked, to run on a machine that can reach the provider, then reads the JSON response back. Equivalent to GOOSE_AUTH_OFFLINE=1."
+        )]
+        offline: bool,
+
+        /// Redirect URL to use from GOOSE_AUTH_REDIRECT_URL
+        #[arg(
+            long,
+            value_name = "URL",
+            help = "Use this entry of GOOSE_AUTH_REDIRECT_URL as the redirect URL",
+            long_help = "Pick the redirect URL explicitly when GOOSE_AUTH_REDIRECT_URL lists several, comma-separated. Without it, the automatic flow uses the entry pointing at this machine (localhost or a loopback address) and the manual flow the https one. With a single configured URL, or none, the given URL is used as is."
+        )]
+        redirect: Option<String>,
     },
     #[command(about = "Show authentication status")]
-    Status,
+    Status {
+        /// Also show the remaining API rate limit
+        #[arg(short, long, help = "Also show the remaining API rate limit")]
+        verbose: bool,
+    },
     #[command(about = "Remove local credentials")]
     Logout,
+    #[command(
+        about = "Revoke the stored token at GitHub and remove it locally",
+        long_about = "Invalidate the stored token server-side through GitHub's OAuth app API, then delete the local copy. Needs GOOSE_GITHUB_CLIENT_ID and GOOSE_GITHUB_CLIENT_SECRET. Use this instead of logout on shared machines."
+    )]
+    Revoke,
+    #[command(
+        about = "Print the stored access token",
+        long_about = "Print the access token for the current provider and account to stdout, and nothing else, so scripts can use $(goose auth token). An expired token is refreshed first. Refuses to run unless --force is given or GOOSE_ALLOW_TOKEN_PRINT=1 is set, so the token does not end up in a shared terminal by accident."
+    )]
+    Token {
+        /// Print even without GOOSE_ALLOW_TOKEN_PRINT=1
+        #[arg(long, help = "Print the token without GOOSE_ALLOW_TOKEN_PRINT=1")]
+        force: bool,
+    },
+    #[command(
+        about = "Renew the stored token with its refresh token now",
+        long_about = "Exchange the stored refresh token for a new access token even if the current one is far from expiring, save the new token and print its expiry. Useful from cron to keep a session alive. Fails when the token came with no refresh token, as with grants that only issue a long-lived access token."
+    )]
+    Refresh,
+    #[command(
+        about = "Show the stored token's user, client id, scopes and rate limit",
+        long_about = "Check the stored token against the provider's identity endpoint and print a table of the user it belongs to, the OAuth app it was issued to (X-OAuth-Client-Id), the scopes it grants (X-OAuth-Scopes) and the API quota left. For OIDC providers, print the claims of the id_token verified at login instead."
+    )]
+    Inspect,
+    #[command(
+        about = "Print the login name of the authenticated user",
+        long_about = "Check the stored token against the provider's identity endpoint and print only the login name, e.g. for a shell prompt. Exits non-zero when not authenticated."
+    )]
+    Whoami,
+    #[command(
+        about = "Print an authorization URL to open yourself",
+        long_about = "Build the PKCE authorization URL the way `goose auth login` does and print it to stdout without starting a callback server. The verifier and state are kept encrypted in the auth directory for ten minutes, so that `goose auth exchange` can finish the login with the URL the browser was redirected to."
+    )]
+    Url,
+    #[command(
+        about = "Finish a login started with `goose auth url`",
+        long_about = "Redeem the code from the URL the browser was redirected to after `goose auth url`, using the verifier and state it stashed. Accepts the full redirected URL, its query string, or the bare code value. A bare code is refused unless the state is given with --state or the check is waived with --skip-state-check. Fails if no login is pending or it is more than ten minutes old."
+    )]
+    Exchange {
+        /// Redirected URL, its query string, or the bare code
+        input: String,
+
+        /// State to check a bare code against
+        #[arg(
+            long,
+            value_name = "STATE",
+            help = "The state parameter from the redirected URL, for a bare code"
+        )]
+        state: Option<String>,
+
+        /// Exchange a bare code without checking the state
+        #[arg(
+            long,
+            conflicts_with = "state",
+            help = "Exchange a bare code without verifying the state parameter"
+        )]
+        skip_state_check: bool,
+    },
+    #[command(
+        about = "Make an account the default for later commands",
+        long_about = "Record NAME as the active account, used whenever --account and GOOSE_AUTH_ACCOUNT are not given. `goose auth status` lists the accounts with a stored token and marks the active one."
+    )]
+    Switch {
+        /// Account to make active
+        name: String,
+    },
+    #[command(
+        about = "Print a short-lived installation access token for a GitHub App",
+        long_about = "Sign a JWT with the GitHub App private key (GOOSE_GITHUB_APP_ID plus GOOSE_GITHUB_APP_PRIVATE_KEY or GOOSE_GITHUB_APP_PRIVATE_KEY_FILE) and exchange it for an installation access token. The token is printed to stdout and its expiry to stderr."
+    )]
+    AppToken {
+        /// Installation to mint the token for
+        #[arg(long, value_name = "ID", help = "GitHub App installation id")]
+        installation: u64,
+    },
+    #[command(
+        about = "Check GOOSE_AUTH_REDIRECT_URL against the GitHub App's callback URLs",
+        long_about = "Fetch the callback URLs registered for the GitHub App (using the GOOSE_GITHUB_APP_ID credentials) and report whether GOOSE_AUTH_REDIRECT_URL is allowed by one of them. When the app's settings are not available through the API, the check is skipped with a note."
+    )]
+    CheckRedirect,
+    #[command(about = "Check the auth configuration and report any problems")]
+    Doctor,
+    #[command(
+        about = "Check that a browser login could start, without logging in",
+        long_about = "Run the checks a login depends on and report each as pass or fail: curl runs when it is the HTTP backend, the client id and redirect URL are set, the redirect URL is valid, the callback port can be bound (it is released straight away) and the provider's authorize endpoint answers a HEAD request. Exits non-zero if any check fails."
+    )]
+    Test,
+    #[command(
+        about = "Remove expired or invalid tokens from the token store",
+        long_about = "Go through every token in the active store (GOOSE_TOKEN_STORE) and delete those whose recorded expiry has passed. With --verify, tokens are also checked against the identity endpoint and removed when rejected."
+    )]
+    Prune {
+        /// Also check tokens against the identity endpoint
+        #[arg(long, help = "Validate each unexpired token against the API")]
+        verify: bool,
+
+        /// Only report what would be removed
+        #[arg(
+            long,
+            help = "List the tokens that would be removed without deleting them"
+        )]
+        dry_run: bool,
+    },
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -756,15 +1026,86 @@ pub async fn cli() -> Result<()> {
     );
 
     match cli.command {
-        Some(Command::Auth { command }) => {
-            match command {
-                AuthCommand::Login { manual } => {
-                    if manual { auth::login_manual_only().await?; } else { auth::login_interactive().await?; }
+        Some(Command::Auth {
+            output,
+            account,
+            provider_config,
+            command,
+        }) => {
+            auth::output::configure(output.as_deref())?;
+            auth::configure_account(account.as_deref())?;
+            auth::configure_provider_config(provider_config.as_deref());
+            let metrics_provider = match &command {
+                AuthCommand::Login { providers, .. } => providers.first().cloned(),
+                _ => None,
+            };
+            let result = match command {
+                AuthCommand::Login {
+                    manual,
+                    scopes,
+                    scopes_file,
+                    no_persist,
+                    reauth,
+                    scopes_exact,
+                    headless,
+                    prompt,
+                    providers,
+                    reuse_port,
+                    unsafe_show_secrets,
+                    timeout_action,
+                    timeout,
+                    no_browser,
+                    offline,
+                    redirect,
+                } => {
+                    let opts = auth::LoginOptions {
+                        scopes,
+                        scopes_file,
+                        no_persist,
+                        reauth,
+                        scopes_exact,
+                        headless,
+                        prompt,
+                        provider: providers.first().cloned(),
+                        reuse_port,
+                        unsafe_show_secrets,
+                        timeout_action,
+                        timeout,
+                        no_browser,
+                        offline,
+                        redirect,
+                    };
+                    if providers.len() > 1 {
+                        auth::login_providers(&opts, manual, &providers).await
+                    } else {
+                        auth::run_login(&opts, manual).await
+                    }
                 }
-                AuthCommand::Status => auth::status().await?,
-                AuthCommand::Logout => auth::logout().await?,
+                AuthCommand::Status { verbose } => auth::status(verbose).await,
+                AuthCommand::Logout => auth::logout().await,
+                AuthCommand::Revoke => auth::revoke().await,
+                AuthCommand::Switch { name } => auth::switch(&name).await,
+                AuthCommand::Token { force } => auth::print_token(force).await,
+                AuthCommand::Refresh => auth::refresh().await,
+                AuthCommand::Inspect => auth::inspect().await,
+                AuthCommand::Whoami => auth::whoami().await,
+                AuthCommand::Url => auth::print_auth_url().await,
+                AuthCommand::Exchange {
+                    input,
+                    state,
+                    skip_state_check,
+                } => auth::exchange(&input, state.as_deref(), skip_state_check).await,
+                AuthCommand::AppToken { installation } => auth::app_token(installation).await,
+                AuthCommand::CheckRedirect => auth::check_redirect().await,
+                AuthCommand::Doctor => auth::doctor().await,
+                AuthCommand::Test => auth::test_config().await,
+                AuthCommand::Prune { verify, dry_run } => auth::prune(verify, dry_run).await,
+            };
+            auth::record_metrics(metrics_provider.as_deref(), result.is_ok()).await;
+            if let Err(e) = &result {
+                auth::output::emit_error(e);
             }
-            return Ok(());
+            return result;
         }
         Some(Command::Configure {}) => {
             let _ = handle_configure().await;
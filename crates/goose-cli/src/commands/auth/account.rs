@@ -0,0 +1,111 @@
+//! Named accounts, so one install can hold e.g. a personal and a work login
+//! per provider. Tokens are stored under `<provider>:<account>`.
+//!
+//! The account for a command is `--account`, else `GOOSE_AUTH_ACCOUNT`, else
+//! the one chosen with `goose auth switch`, else `default`.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::token_store::{self, write_atomic};
+
+pub const DEFAULT_ACCOUNT: &str = "default";
+const ACTIVE_ACCOUNT_FILE: &str = "active_account";
+
+/// `--account` for this invocation
+static FLAG: Mutex<Option<String>> = Mutex::new(None);
+
+/// Account names end up in store keys and file names, so keep them simple.
+pub fn validate(name: &str) -> Result<String> {
+    let name = name.trim();
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(anyhow!(
+            "Invalid account name '{}': use letters, digits, '-', '_' or '.'",
+            name
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Remember `--account` for [`current`]. Rejects malformed names up front.
+pub fn configure(flag: Option<&str>) -> Result<()> {
+    let flag = flag.map(validate).transpose()?;
+    *FLAG.lock().unwrap() = flag;
+    Ok(())
+}
+
+/// The account this invocation operates on.
+pub fn current() -> Result<String> {
+    if let Some(flag) = FLAG.lock().unwrap().clone() {
+        return Ok(flag);
+    }
+    if let Some(env) = std::env::var("GOOSE_AUTH_ACCOUNT")
+        .ok()
+        .filter(|env| !env.trim().is_empty())
+    {
+        return validate(&env);
+    }
+    active_in(&token_store::auth_dir()?)
+}
+
+fn active_in(dir: &Path) -> Result<String> {
+    match std::fs::read_to_string(dir.join(ACTIVE_ACCOUNT_FILE)) {
+        Ok(name) => validate(&name),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(DEFAULT_ACCOUNT.to_string()),
+        Err(e) => Err(e).context("Failed to read the active account"),
+    }
+}
+
+/// Make `name` the account used when none is given.
+pub fn set_active(name: &str) -> Result<()> {
+    set_active_in(&token_store::auth_dir()?, name)
+}
+
+fn set_active_in(dir: &Path, name: &str) -> Result<()> {
+    if token_store::persistence_disabled() {
+        return Err(anyhow!("Cannot switch accounts: persistence is disabled"));
+    }
+    write_atomic(&dir.join(ACTIVE_ACCOUNT_FILE), validate(name)?.as_bytes())
+}
+
+/// Accounts with a token stored for `provider`, given the store's keys.
+pub fn accounts_in(keys: &[String], provider: &str) -> Vec<String> {
+    let prefix = format!("{}:", provider);
+    let mut accounts: Vec<String> = keys
+        .iter()
+        .filter_map(|key| key.strip_prefix(&prefix))
+        .map(str::to_string)
+        .collect();
+    accounts.sort();
+    accounts.dedup();
+    accounts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_changes_active_account() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(active_in(dir.path()).unwrap(), DEFAULT_ACCOUNT);
+        set_active_in(dir.path(), "work").unwrap();
+        assert_eq!(active_in(dir.path()).unwrap(), "work");
+        assert!(set_active_in(dir.path(), "../evil").is_err());
+    }
+
+    #[test]
+    fn test_accounts_for_provider() {
+        let keys = [
+            "github:default".to_string(),
+            "github:work".to_string(),
+            "gitlab:default".to_string(),
+        ];
+        assert_eq!(accounts_in(&keys, "github"), ["default", "work"]);
+    }
+}
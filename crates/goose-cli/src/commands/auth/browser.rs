@@ -0,0 +1,197 @@
+use anyhow::{anyhow, Context, Result};
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+const DEFAULT_OPEN_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug)]
+pub enum OpenOutcome {
+    Exited(ExitStatus),
+    /// The command outlived its deadline and was killed
+    TimedOut,
+}
+
+/// Variables naming a browser command, in order of precedence
+const OPEN_COMMAND_VARS: &[&str] = &["GOOSE_AUTH_OPEN_CMD", "GOOSE_BROWSER"];
+
+/// Open `url` in the user's browser.
+///
+/// `GOOSE_AUTH_OPEN_CMD`, or `GOOSE_BROWSER` (e.g. `google-chrome
+/// --profile-directory=Work {{url}}`), replaces the OS default with a custom
+/// command. The URL is substituted for a `{{url}}` placeholder, or appended as
+/// the last argument when there is none. The command gets
+/// `GOOSE_AUTH_OPEN_TIMEOUT_SECS` (default 10) to exit before it is killed, so
+/// a broken opener can never wedge the login.
+pub async fn open_browser(url: &str) -> Result<()> {
+    match open_command() {
+        Some((var, template)) => {
+            open_with_command(
+                var,
+                &template,
+                url,
+                super::env_secs("GOOSE_AUTH_OPEN_TIMEOUT_SECS", DEFAULT_OPEN_TIMEOUT_SECS)?,
+            )
+            .await
+        }
+        None => webbrowser::open(url).map_err(anyhow::Error::from),
+    }
+}
+
+/// The configured browser command and the variable it came from.
+fn open_command() -> Option<(&'static str, String)> {
+    OPEN_COMMAND_VARS.iter().find_map(|var| {
+        std::env::var(var)
+            .ok()
+            .filter(|cmd| !cmd.trim().is_empty())
+            .map(|cmd| (*var, cmd))
+    })
+}
+
+async fn open_with_command(var: &str, template: &str, url: &str, limit: Duration) -> Result<()> {
+    let argv = command_argv(var, template, url)?;
+    match run_open_command(&argv, limit).await? {
+        OpenOutcome::Exited(status) if status.success() => {
+            tracing::debug!("{} exited with {}", var, status);
+            Ok(())
+        }
+        OpenOutcome::Exited(status) => Err(anyhow!("{} exited with {}", var, status)),
+        OpenOutcome::TimedOut => Err(anyhow!(
+            "{} did not exit within {}s and was killed",
+            var,
+            limit.as_secs_f32()
+        )),
+    }
+}
+
+fn command_argv(var: &str, template: &str, url: &str) -> Result<Vec<String>> {
+    let mut argv = shlex::split(template)
+        .filter(|argv| !argv.is_empty())
+        .ok_or_else(|| anyhow!("{} could not be parsed: {}", var, template))?;
+    if argv.iter().any(|arg| arg.contains("{{url}}")) {
+        for arg in argv.iter_mut() {
+            *arg = arg.replace("{{url}}", url);
+        }
+    } else {
+        argv.push(url.to_string());
+    }
+    Ok(argv)
+}
+
+/// Run an opener command, killing it and (on Unix) its whole process group if
+/// it has not exited within `limit`.
+pub async fn run_open_command(argv: &[String], limit: Duration) -> Result<OpenOutcome> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| anyhow!("Empty browser command"))?;
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to run browser command {}", program))?;
+    match tokio::time::timeout(limit, child.wait()).await {
+        Ok(status) => Ok(OpenOutcome::Exited(status?)),
+        Err(_) => {
+            kill_tree(&mut child).await;
+            Ok(OpenOutcome::TimedOut)
+        }
+    }
+}
+
+async fn kill_tree(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+        if let Some(pid) = child.id() {
+            let _ = killpg(Pid::from_raw(pid as i32), Signal::SIGKILL);
+        }
+    }
+    let _ = child.kill().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_argv_placeholder_and_append() {
+        assert_eq!(
+            command_argv("GOOSE_BROWSER", "firefox -P work {{url}}", "https://x").unwrap(),
+            vec!["firefox", "-P", "work", "https://x"]
+        );
+        assert_eq!(
+            command_argv("GOOSE_BROWSER", "open -a 'Google Chrome'", "https://x").unwrap(),
+            vec!["open", "-a", "Google Chrome", "https://x"]
+        );
+    }
+
+    #[test]
+    fn test_open_command_precedence() {
+        temp_env::with_vars(
+            [
+                ("GOOSE_AUTH_OPEN_CMD", None),
+                ("GOOSE_BROWSER", Some("firefox -P work")),
+            ],
+            || {
+                assert_eq!(
+                    open_command(),
+                    Some(("GOOSE_BROWSER", "firefox -P work".to_string()))
+                );
+            },
+        );
+        temp_env::with_vars(
+            [
+                ("GOOSE_AUTH_OPEN_CMD", Some("xdg-open")),
+                ("GOOSE_BROWSER", Some("firefox -P work")),
+            ],
+            || {
+                assert_eq!(
+                    open_command(),
+                    Some(("GOOSE_AUTH_OPEN_CMD", "xdg-open".to_string()))
+                );
+            },
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hung_open_command_is_killed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let marker = dir.path().join("still-running");
+        let script = format!("sleep 1; touch {}", marker.display());
+        let argv = vec!["sh".to_string(), "-c".to_string(), script];
+
+        let started = std::time::Instant::now();
+        let outcome = run_open_command(&argv, Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert!(matches!(outcome, OpenOutcome::TimedOut));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        // The whole process group is gone, so the script never reaches `touch`
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_open_command_exit_status_is_reported() {
+        let argv = vec!["sh".to_string(), "-c".to_string(), "exit 3".to_string()];
+        match run_open_command(&argv, Duration::from_secs(5))
+            .await
+            .unwrap()
+        {
+            OpenOutcome::Exited(status) => assert_eq!(status.code(), Some(3)),
+            OpenOutcome::TimedOut => panic!("command should have exited"),
+        }
+    }
+}
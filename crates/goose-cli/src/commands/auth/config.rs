@@ -0,0 +1,530 @@
+//! What a browser login needs to know before it starts: the provider and its
+//! endpoints, the OAuth app's credentials, the redirect URL and the scopes.
+//!
+//! [`AuthConfig::from_env`] resolves these from flags and environment
+//! variables; a caller that already knows them, such as a test pointing the
+//! flow at a mock server, builds the struct directly.
+//!
+//! Settings that would otherwise be exported in every shell can live in
+//! `~/.config/goose/auth.toml` (or the file named by `GOOSE_CONFIG`):
+//!
+//! ```toml
+//! provider = "github"
+//! client_id = "Iv1.0123456789abcdef"
+//! redirect_url = "http://127.0.0.1:8080/oauth_callback"
+//! scopes = ["read:user", "repo"]
+//! client_auth_method = "basic"
+//! ```
+//!
+//! `redirect_url` may list several registered callbacks separated by commas,
+//! e.g. a localhost one for development and an https one for production: the
+//! automatic flow takes the one pointing at this machine, the manual flow the
+//! https one, and `--redirect` picks one explicitly.
+//!
+//! The environment variable wins whenever both are set. `client_secret` is
+//! only read from the file when it also sets `allow_secret = true`; tokens are
+//! never stored there.
+//!
+//! For local development, a build with the `dotenv` feature also reads a
+//! `.env` file (found from the current directory upwards) when `GOOSE_DOTENV=1`
+//! is set. The real environment wins over `.env`, and both over auth.toml.
+
+use anyhow::{anyhow, Context, Result};
+use etcetera::{choose_app_strategy, AppStrategy};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use super::provider::{ClientAuthMethod, Provider};
+use super::redact::SensitiveString;
+use super::{
+    identity, requested_scopes, token_store, validate_redirect_url, AuthError, LoginOptions,
+    DEFAULT_LISTEN_ADDR,
+};
+
+const CONFIG_FILE: &str = "auth.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthConfig {
+    /// Endpoints (`authorize_url`, `token_url`, `user_url`), the identity
+    /// field and the token namespace
+    pub provider: Provider,
+    pub client_id: String,
+    pub client_secret: Option<SensitiveString>,
+    pub redirect_url: String,
+    /// Scopes to request, already merged with the stored token's grant
+    pub scopes: Vec<String>,
+    /// How the client secret reaches the token endpoint
+    pub client_auth_method: ClientAuthMethod,
+}
+
+impl AuthConfig {
+    /// Settings for a browser flow. `serves_callback` is true when goose's own
+    /// callback server receives the redirect; a redirect URL without a path
+    /// then gets `/oauth_callback`, the path that server answers on.
+    pub async fn from_env(opts: &LoginOptions, serves_callback: bool) -> Result<Self> {
+        // Before the scope lookup below reads the token store
+        if opts.no_persist {
+            token_store::disable_persistence();
+        }
+        let client_id = client_id()?;
+        let redirect_url = redirect_url(opts.redirect.as_deref(), serves_callback)?.ok_or_else(|| {
+            AuthError::ConfigMissing("GOOSE_AUTH_REDIRECT_URL (or redirect_url in auth.toml) must be set to a stable HTTPS callback URL".into())
+        })?;
+        let served = validate_redirect_url(&redirect_url, serves_callback)?;
+        let redirect_url = if serves_callback {
+            served.to_string()
+        } else {
+            redirect_url
+        };
+
+        let provider = Provider::resolve(opts.provider.as_deref()).await?;
+        let scopes = requested_scopes(opts, &provider)?;
+        let client_auth_method = client_auth_method(&provider)?;
+        Ok(Self {
+            provider,
+            client_id,
+            client_secret: client_secret().await?,
+            redirect_url,
+            scopes,
+            client_auth_method,
+        })
+    }
+}
+
+/// The redirect URL for a flow, picked from `GOOSE_AUTH_REDIRECT_URL` as the
+/// module docs describe; `explicit` is `--redirect`. `None` when neither is
+/// set.
+pub fn redirect_url(explicit: Option<&str>, serves_callback: bool) -> Result<Option<String>> {
+    let configured = setting("GOOSE_AUTH_REDIRECT_URL")?;
+    // `dual` and Unix sockets have no port of their own to match
+    let listen_port = std::env::var("GOOSE_AUTH_LISTEN_ADDR")
+        .unwrap_or_else(|_| DEFAULT_LISTEN_ADDR.to_string())
+        .trim()
+        .parse::<std::net::SocketAddr>()
+        .ok()
+        .map(|addr| addr.port());
+    select_redirect_url(
+        configured.as_deref(),
+        explicit,
+        serves_callback,
+        listen_port,
+    )
+}
+
+fn select_redirect_url(
+    configured: Option<&str>,
+    explicit: Option<&str>,
+    serves_callback: bool,
+    listen_port: Option<u16>,
+) -> Result<Option<String>> {
+    let entries: Vec<&str> = configured
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    if let Some(explicit) = explicit.map(str::trim) {
+        if entries.is_empty() || entries.contains(&explicit) {
+            return Ok(Some(explicit.to_string()));
+        }
+        return Err(anyhow!(
+            "--redirect {} is not one of the GOOSE_AUTH_REDIRECT_URL entries ({})",
+            explicit,
+            entries.join(", ")
+        ));
+    }
+    if entries.len() <= 1 {
+        return Ok(entries.first().map(|entry| entry.to_string()));
+    }
+    let parsed: Vec<(&str, url::Url)> = entries
+        .iter()
+        .filter_map(|entry| url::Url::parse(entry).ok().map(|url| (*entry, url)))
+        .collect();
+    let chosen = if serves_callback {
+        let loopback: Vec<_> = parsed
+            .iter()
+            .filter(|(_, url)| identity::is_loopback(url))
+            .collect();
+        let chosen = loopback
+            .iter()
+            .find(|(_, url)| url.port_or_known_default() == listen_port)
+            .or(loopback.first());
+        Some(chosen.map(|(entry, _)| *entry).ok_or_else(|| {
+            anyhow!(
+                "None of the GOOSE_AUTH_REDIRECT_URL entries points at this machine, where the callback server listens; add a localhost one or pick one with --redirect"
+            )
+        })?)
+    } else {
+        parsed
+            .iter()
+            .find(|(_, url)| url.scheme() == "https")
+            .map(|(entry, _)| *entry)
+    };
+    Ok(Some(chosen.unwrap_or(entries[0]).to_string()))
+}
+
+/// The OAuth app's client id, which every flow needs.
+pub fn client_id() -> Result<String> {
+    setting("GOOSE_GITHUB_CLIENT_ID")?.ok_or_else(|| {
+        AuthError::ConfigMissing(
+            "GOOSE_GITHUB_CLIENT_ID (or client_id in auth.toml) is required for GitHub OAuth"
+                .into(),
+        )
+        .into()
+    })
+}
+
+/// `GOOSE_AUTH_CLIENT_AUTH_METHOD`: `body` sends the client secret in the
+/// token request's form, `basic` as HTTP Basic credentials (RFC 6749 section
+/// 2.3.1), which strict confidential-client servers require. Without it the
+/// provider's own method applies, which is `body` for every preset.
+pub fn client_auth_method(provider: &Provider) -> Result<ClientAuthMethod> {
+    match setting("GOOSE_AUTH_CLIENT_AUTH_METHOD")? {
+        Some(raw) => parse_client_auth_method(&raw),
+        None => Ok(provider.client_auth_method),
+    }
+}
+
+fn parse_client_auth_method(raw: &str) -> Result<ClientAuthMethod> {
+    match raw.trim().to_lowercase().as_str() {
+        "body" | "client_secret_post" => Ok(ClientAuthMethod::ClientSecretPost),
+        "basic" | "client_secret_basic" => Ok(ClientAuthMethod::ClientSecretBasic),
+        _ => Err(anyhow!(
+            "GOOSE_AUTH_CLIENT_AUTH_METHOD must be body or basic, got '{}'",
+            raw
+        )),
+    }
+}
+
+/// The OAuth app's client secret, if any: the stdout of
+/// `GOOSE_GITHUB_CLIENT_SECRET_CMD` (e.g. a vault lookup), else the contents
+/// of `GOOSE_GITHUB_CLIENT_SECRET_FILE`, else `GOOSE_GITHUB_CLIENT_SECRET`.
+/// The first two keep the secret out of process listings and shell history.
+pub async fn client_secret() -> Result<Option<SensitiveString>> {
+    let var = |name| {
+        std::env::var(name)
+            .ok()
+            .filter(|value: &String| !value.trim().is_empty())
+    };
+    if let Some(command) = var("GOOSE_GITHUB_CLIENT_SECRET_CMD") {
+        return secret_from_command(&command).await.map(|s| Some(s.into()));
+    }
+    if let Some(path) = var("GOOSE_GITHUB_CLIENT_SECRET_FILE") {
+        return secret_from_file(Path::new(path.trim())).map(|s| Some(s.into()));
+    }
+    Ok(setting("GOOSE_GITHUB_CLIENT_SECRET")?.map(SensitiveString::from))
+}
+
+async fn secret_from_command(command: &str) -> Result<String> {
+    let argv = shlex::split(command)
+        .filter(|argv| !argv.is_empty())
+        .ok_or_else(|| anyhow!("GOOSE_GITHUB_CLIENT_SECRET_CMD could not be parsed"))?;
+    // stdin and stderr stay attached so the command can ask for a passphrase
+    let output = tokio::process::Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .await
+        .with_context(|| format!("Failed to run GOOSE_GITHUB_CLIENT_SECRET_CMD ({})", argv[0]))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "GOOSE_GITHUB_CLIENT_SECRET_CMD exited with {}",
+            output.status
+        ));
+    }
+    let secret = String::from_utf8(output.stdout)
+        .map_err(|_| anyhow!("GOOSE_GITHUB_CLIENT_SECRET_CMD printed a non-UTF-8 secret"))?;
+    non_empty_secret(&secret, "GOOSE_GITHUB_CLIENT_SECRET_CMD printed nothing")
+}
+
+fn secret_from_file(path: &Path) -> Result<String> {
+    let secret = std::fs::read_to_string(path).map_err(|e| {
+        anyhow!(
+            "GOOSE_GITHUB_CLIENT_SECRET_FILE points at {}, which cannot be read: {}",
+            path.display(),
+            e
+        )
+    })?;
+    non_empty_secret(
+        &secret,
+        &format!(
+            "GOOSE_GITHUB_CLIENT_SECRET_FILE points at {}, which is empty",
+            path.display()
+        ),
+    )
+}
+
+// Files and commands usually end the secret with a newline; nothing else is
+// stripped, since whitespace could be part of it.
+fn non_empty_secret(raw: &str, empty: &str) -> Result<String> {
+    let secret = raw.trim_end_matches(['\r', '\n']);
+    if secret.is_empty() {
+        return Err(anyhow!("{}", empty));
+    }
+    Ok(secret.to_string())
+}
+
+static DOTENV: std::sync::Once = std::sync::Once::new();
+
+/// Load `.env` into the environment once, if `GOOSE_DOTENV=1` asks for it.
+/// Variables that are already set keep their values.
+pub fn load_dotenv() {
+    if std::env::var("GOOSE_DOTENV").unwrap_or_default() != "1" {
+        return;
+    }
+    DOTENV.call_once(|| {
+        #[cfg(feature = "dotenv")]
+        match dotenvy::dotenv() {
+            Ok(path) => tracing::debug!(path = %path.display(), "loaded .env"),
+            Err(e) if e.not_found() => {
+                eprintln!("[oauth-info] GOOSE_DOTENV=1 but no .env file was found")
+            }
+            Err(e) => eprintln!("[oauth-info] Ignoring .env: {}", e),
+        }
+        #[cfg(not(feature = "dotenv"))]
+        eprintln!(
+            "[oauth-info] GOOSE_DOTENV=1 needs a goose built with the dotenv feature; ignoring it"
+        );
+    });
+}
+
+/// The value of `var`, falling back to the matching auth.toml entry.
+pub fn setting(var: &str) -> Result<Option<String>> {
+    if let Some(value) = std::env::var(var)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+    {
+        return Ok(Some(value));
+    }
+    Ok(FileConfig::load()?.get(var))
+}
+
+/// `GOOSE_CONFIG`, else `auth.toml` in goose's config directory.
+pub fn config_path() -> Result<PathBuf> {
+    if let Some(path) = std::env::var("GOOSE_CONFIG")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+    {
+        return Ok(PathBuf::from(path.trim()));
+    }
+    Ok(choose_app_strategy(crate::APP_STRATEGY.clone())
+        .context("goose requires a home dir")?
+        .in_config_dir(CONFIG_FILE))
+}
+
+/// The config file in use, or None when there is none. Fails when the file
+/// cannot be read or parsed.
+pub fn check_file() -> Result<Option<PathBuf>> {
+    let path = config_path()?;
+    FileConfig::load()?;
+    Ok(path.exists().then_some(path))
+}
+
+/// `scopes` may be a list or a single space- or comma-separated string.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub(super) enum FileScopes {
+    List(Vec<String>),
+    Joined(String),
+}
+
+impl FileScopes {
+    pub(super) fn joined(&self) -> String {
+        match self {
+            FileScopes::List(list) => list.join(" "),
+            FileScopes::Joined(joined) => joined.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    provider: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<SensitiveString>,
+    redirect_url: Option<String>,
+    scopes: Option<FileScopes>,
+    client_auth_method: Option<String>,
+    #[serde(default)]
+    allow_secret: bool,
+}
+
+impl FileConfig {
+    /// The config file, or an empty one when the default path does not
+    /// exist. A file named by `GOOSE_CONFIG` has to exist.
+    fn load() -> Result<Self> {
+        let explicit = std::env::var("GOOSE_CONFIG")
+            .map(|path| !path.trim().is_empty())
+            .unwrap_or(false);
+        Self::load_from(&config_path()?, explicit)
+    }
+
+    fn load_from(path: &Path, required: bool) -> Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(e) => {
+                return Err(anyhow!(
+                    "Failed to read auth config {}: {}",
+                    path.display(),
+                    e
+                ))
+            }
+        };
+        Self::parse(&contents).with_context(|| format!("Invalid auth config {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let config: Self = toml::from_str(contents)?;
+        if config.client_secret.is_some() && !config.allow_secret {
+            return Err(anyhow!(
+                "client_secret is only read from this file with allow_secret = true; prefer GOOSE_GITHUB_CLIENT_SECRET"
+            ));
+        }
+        Ok(config)
+    }
+
+    fn get(&self, var: &str) -> Option<String> {
+        let value = match var {
+            "GOOSE_AUTH_PROVIDER" => self.provider.clone(),
+            "GOOSE_GITHUB_CLIENT_ID" => self.client_id.clone(),
+            "GOOSE_GITHUB_CLIENT_SECRET" => self
+                .client_secret
+                .as_ref()
+                .map(|secret| secret.expose().to_string()),
+            "GOOSE_AUTH_REDIRECT_URL" => self.redirect_url.clone(),
+            "GOOSE_GITHUB_SCOPES" => self.scopes.as_ref().map(FileScopes::joined),
+            "GOOSE_AUTH_CLIENT_AUTH_METHOD" => self.client_auth_method.clone(),
+            _ => None,
+        };
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_settings_map_to_env_names() {
+        let config = FileConfig::parse(
+            r#"
+            provider = "gitlab"
+            client_id = "abc"
+            redirect_url = "http://127.0.0.1:8080/cb"
+            scopes = ["read_user", "api"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.get("GOOSE_AUTH_PROVIDER").as_deref(), Some("gitlab"));
+        assert_eq!(config.get("GOOSE_GITHUB_CLIENT_ID").as_deref(), Some("abc"));
+        assert_eq!(
+            config.get("GOOSE_GITHUB_SCOPES").as_deref(),
+            Some("read_user api")
+        );
+        assert_eq!(config.get("GOOSE_GITHUB_CLIENT_SECRET"), None);
+    }
+
+    #[test]
+    fn test_file_secret_needs_allow_secret() {
+        let err = FileConfig::parse(r#"client_secret = "s3cret""#).unwrap_err();
+        assert!(err.to_string().contains("allow_secret = true"));
+        let config = FileConfig::parse("client_secret = \"s3cret\"\nallow_secret = true").unwrap();
+        assert_eq!(
+            config.get("GOOSE_GITHUB_CLIENT_SECRET").as_deref(),
+            Some("s3cret")
+        );
+        assert!(FileConfig::parse("token = \"gho_x\"").is_err());
+    }
+
+    #[test]
+    fn test_client_auth_method_names() {
+        for (raw, method) in [
+            ("body", ClientAuthMethod::ClientSecretPost),
+            ("Basic", ClientAuthMethod::ClientSecretBasic),
+            ("client_secret_basic", ClientAuthMethod::ClientSecretBasic),
+        ] {
+            assert_eq!(parse_client_auth_method(raw).unwrap(), method);
+        }
+        assert!(parse_client_auth_method("header").is_err());
+        let config = FileConfig::parse(r#"client_auth_method = "basic""#).unwrap();
+        assert_eq!(
+            config.get("GOOSE_AUTH_CLIENT_AUTH_METHOD").as_deref(),
+            Some("basic")
+        );
+    }
+
+    #[test]
+    fn test_redirect_url_follows_mode() {
+        let list =
+            Some("https://auth.example.com/cb, http://127.0.0.1:9000/cb,http://localhost:8080/cb");
+        let select = |explicit, serves_callback| {
+            select_redirect_url(list, explicit, serves_callback, Some(8080))
+        };
+        assert_eq!(
+            select(None, true).unwrap().as_deref(),
+            Some("http://localhost:8080/cb")
+        );
+        assert_eq!(
+            select(None, false).unwrap().as_deref(),
+            Some("https://auth.example.com/cb")
+        );
+        assert_eq!(
+            select(Some("http://127.0.0.1:9000/cb"), true)
+                .unwrap()
+                .as_deref(),
+            Some("http://127.0.0.1:9000/cb")
+        );
+        assert!(select(Some("https://other.example.com/cb"), false).is_err());
+
+        let remote = Some("https://auth.example.com/cb,https://staging.example.com/cb");
+        assert!(select_redirect_url(remote, None, true, Some(8080)).is_err());
+        // A single entry is used as is, as before lists were accepted
+        assert_eq!(
+            select_redirect_url(Some("https://auth.example.com/cb"), None, true, Some(8080))
+                .unwrap()
+                .as_deref(),
+            Some("https://auth.example.com/cb")
+        );
+    }
+
+    #[test]
+    fn test_secret_file_drops_trailing_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret");
+        std::fs::write(&path, "s3cret \n").unwrap();
+        assert_eq!(secret_from_file(&path).unwrap(), "s3cret ");
+        std::fs::write(&path, "\r\n").unwrap();
+        assert!(secret_from_file(&path)
+            .unwrap_err()
+            .to_string()
+            .contains("empty"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_secret_command_output() {
+        let secret = secret_from_command("printf 's3cret\\n'").await.unwrap();
+        assert_eq!(secret, "s3cret");
+        let err = secret_from_command("sh -c 'exit 2'").await.unwrap_err();
+        assert!(err.to_string().contains("exited with"));
+    }
+
+    #[test]
+    fn test_missing_file_is_empty_unless_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        assert_eq!(
+            FileConfig::load_from(&path, false).unwrap(),
+            FileConfig::default()
+        );
+        assert!(FileConfig::load_from(&path, true).is_err());
+    }
+}
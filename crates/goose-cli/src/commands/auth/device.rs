@@ -0,0 +1,269 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use super::{http, redact, AuthError};
+
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// RFC 8628 section 3.5: each `slow_down` adds five seconds to the interval.
+const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+/// Most random delay added to each wait, on top of the interval, so clients
+/// started together do not poll in step
+const MAX_POLL_JITTER: Duration = Duration::from_millis(500);
+
+/// Response from the device authorization endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_in: u64,
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// What a single poll of the token endpoint told us.
+#[derive(Debug)]
+pub enum PollStatus {
+    Pending,
+    SlowDown(Option<u64>),
+    Authorized(Value),
+    Expired,
+    Denied,
+    Failed(String),
+}
+
+pub fn classify_poll_response(json: Value) -> PollStatus {
+    if json.get("access_token").and_then(Value::as_str).is_some() {
+        return PollStatus::Authorized(json);
+    }
+    let description = json
+        .get("error_description")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    match json.get("error").and_then(Value::as_str) {
+        Some("authorization_pending") => PollStatus::Pending,
+        Some("slow_down") => PollStatus::SlowDown(json.get("interval").and_then(Value::as_u64)),
+        Some("expired_token") => PollStatus::Expired,
+        Some("access_denied") => PollStatus::Denied,
+        Some(error) => PollStatus::Failed(match description {
+            Some(description) => format!("{} ({})", error, description),
+            None => error.to_string(),
+        }),
+        None => PollStatus::Failed("response had neither access_token nor error".to_string()),
+    }
+}
+
+/// Start a device authorization at the provider's `device_url`.
+pub async fn request_device_code(
+    device_url: &str,
+    client_id: &str,
+    scopes: &str,
+) -> Result<DeviceAuthorization> {
+    let response = http::build_http_client()?
+        .post_form(device_url, &[("client_id", client_id), ("scope", scopes)])
+        .await?;
+    if !response.is_success() {
+        return Err(anyhow!(
+            "Device code request returned HTTP {}: {}",
+            response.status,
+            redact::redact(response.body.trim())
+        ));
+    }
+    serde_json::from_value(response.json()?)
+        .map_err(|e| anyhow!("Unexpected device code response: {}", e))
+}
+
+/// Ask the provider's `token_url` once whether the user has approved.
+pub async fn poll_once(token_url: &str, client_id: &str, device_code: &str) -> Result<Value> {
+    http::build_http_client()?
+        .post_form(
+            token_url,
+            &[
+                ("client_id", client_id),
+                ("device_code", device_code),
+                ("grant_type", DEVICE_GRANT_TYPE),
+            ],
+        )
+        .await?
+        .json()
+}
+
+/// Poll until the user approves, denies, or the device code expires.
+///
+/// `poll` performs one token request; `on_wait` is called before every sleep
+/// with the time left until the code expires, so callers can report progress.
+/// Polls are never closer together than the interval, which grows with every
+/// `slow_down`, and each wait adds up to half a second of jitter.
+pub async fn poll_for_token<P, Fut, W>(
+    device: &DeviceAuthorization,
+    poll: P,
+    on_wait: W,
+) -> Result<Value>
+where
+    P: FnMut() -> Fut,
+    Fut: Future<Output = Result<Value>>,
+    W: FnMut(Duration),
+{
+    poll_with_sleep(device, poll, on_wait, tokio::time::sleep).await
+}
+
+// `poll_for_token` with the sleep passed in, so tests can see the waits
+// without taking them.
+async fn poll_with_sleep<P, Fut, W, S, SleepFut>(
+    device: &DeviceAuthorization,
+    mut poll: P,
+    mut on_wait: W,
+    mut sleep: S,
+) -> Result<Value>
+where
+    P: FnMut() -> Fut,
+    Fut: Future<Output = Result<Value>>,
+    W: FnMut(Duration),
+    S: FnMut(Duration) -> SleepFut,
+    SleepFut: Future<Output = ()>,
+{
+    let deadline = Instant::now() + Duration::from_secs(device.expires_in);
+    let mut interval = Duration::from_secs(device.interval.max(1));
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(AuthError::DeviceCodeExpired.into());
+        }
+        on_wait(remaining);
+        sleep((interval + jitter()).min(remaining)).await;
+
+        match classify_poll_response(poll().await?) {
+            PollStatus::Pending => {}
+            PollStatus::SlowDown(suggested) => {
+                let stepped = interval + SLOW_DOWN_STEP;
+                interval = suggested
+                    .map(Duration::from_secs)
+                    .map_or(stepped, |suggested| suggested.max(stepped));
+            }
+            PollStatus::Authorized(json) => return Ok(json),
+            PollStatus::Expired => return Err(AuthError::DeviceCodeExpired.into()),
+            PollStatus::Denied => return Err(AuthError::AccessDenied.into()),
+            PollStatus::Failed(reason) => {
+                return Err(anyhow!("Device authorization failed: {}", reason))
+            }
+        }
+    }
+}
+
+fn jitter() -> Duration {
+    use rand::Rng;
+    let max = MAX_POLL_JITTER.as_millis() as u64;
+    Duration::from_millis(rand::thread_rng().gen_range(0..=max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_classify_poll_response() {
+        assert!(matches!(
+            classify_poll_response(json!({"error": "authorization_pending"})),
+            PollStatus::Pending
+        ));
+        assert!(matches!(
+            classify_poll_response(json!({"error": "slow_down", "interval": 10})),
+            PollStatus::SlowDown(Some(10))
+        ));
+        assert!(matches!(
+            classify_poll_response(json!({"access_token": "t", "token_type": "bearer"})),
+            PollStatus::Authorized(_)
+        ));
+        assert!(matches!(
+            classify_poll_response(json!({"error": "expired_token"})),
+            PollStatus::Expired
+        ));
+        assert!(matches!(
+            classify_poll_response(json!({"error": "access_denied"})),
+            PollStatus::Denied
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_slow_down_lengthens_the_interval() {
+        let device = DeviceAuthorization {
+            device_code: "dc".to_string(),
+            user_code: "ABCD-1234".to_string(),
+            verification_uri: "https://github.com/login/device".to_string(),
+            expires_in: 60,
+            interval: 1,
+        };
+        let mut polls = 0;
+        let mut waits = Vec::new();
+        let json = poll_with_sleep(
+            &device,
+            || {
+                polls += 1;
+                let reply = if polls == 1 {
+                    json!({"error": "slow_down"})
+                } else {
+                    json!({"access_token": "t"})
+                };
+                async move { Ok(reply) }
+            },
+            |_| {},
+            |wait| {
+                waits.push(wait);
+                std::future::ready(())
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(json["access_token"], "t");
+        assert_eq!(waits.len(), 2);
+        let first = Duration::from_secs(1);
+        let second = first + SLOW_DOWN_STEP;
+        assert!(waits[0] >= first && waits[0] <= first + MAX_POLL_JITTER);
+        assert!(waits[1] >= second && waits[1] <= second + MAX_POLL_JITTER);
+    }
+
+    #[tokio::test]
+    async fn test_denied_and_expired_are_distinct_errors() {
+        let device = DeviceAuthorization {
+            device_code: "dc".to_string(),
+            user_code: "ABCD-1234".to_string(),
+            verification_uri: "https://github.com/login/device".to_string(),
+            expires_in: 60,
+            interval: 0,
+        };
+        let err = poll_for_token(
+            &device,
+            || async { Ok(json!({"error": "access_denied"})) },
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AuthError>(),
+            Some(AuthError::AccessDenied)
+        ));
+
+        let err = poll_for_token(
+            &device,
+            || async { Ok(json!({"error": "expired_token"})) },
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AuthError>(),
+            Some(AuthError::DeviceCodeExpired)
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+use thiserror::Error;
+
+/// Auth failures callers may need to tell apart from other errors.
+///
+/// Each kind has its own process exit status, so scripts can branch on it:
+///
+/// | Code | Error               | Meaning                                     |
+/// |------|---------------------|---------------------------------------------|
+/// | 0    |                     | Success                                     |
+/// | 1    |                     | Any other failure                           |
+/// | 3    | `ConfigMissing`     | A required setting is not configured        |
+/// | 4    | `NetworkError`      | A request could not reach the provider      |
+/// | 5    | `StateMismatch`     | The returned `state` did not match          |
+/// | 6    | `Timeout`           | The OAuth callback never arrived            |
+/// | 7    | `NotAuthenticated`  | No usable stored token                      |
+/// | 8    | `TokenRejected`     | The provider refused a token                |
+/// | 9    | `AccessDenied`      | The user declined the authorization         |
+/// | 10   | `DeviceCodeExpired` | The device code ran out                     |
+/// | 11   | `RateLimited`       | The provider's rate limit was hit           |
+/// | 12   | `Cancelled`         | The embedding application cancelled it      |
+/// | 130  | `UserCancelled`     | Interrupted with Ctrl-C                     |
+#[derive(Debug, Error)]
+pub enum AuthError {
+    /// A setting the command needs is not configured; carries the message
+    /// naming it
+    #[error("{0}")]
+    ConfigMissing(String),
+    /// The request never got an HTTP response (DNS, TLS, connection reset)
+    #[error("{0}")]
+    NetworkError(String),
+    /// The `state` coming back did not match the one sent, naming where it
+    /// came from
+    #[error("State mismatch in {0}")]
+    StateMismatch(&'static str),
+    /// The OAuth callback did not arrive within the wait, in seconds
+    #[error("Timed out after {0}s waiting for the OAuth callback")]
+    Timeout(u64),
+    /// The device code ran out before the user approved it
+    #[error("The device code expired before it was authorized; start the login again")]
+    DeviceCodeExpired,
+    /// The user declined the authorization request
+    #[error("Authorization was denied by the user")]
+    AccessDenied,
+    /// No stored token, or the identity endpoint rejected it
+    #[error("Not authenticated")]
+    NotAuthenticated,
+    /// The provider refused a token it had just issued, or a refresh token
+    #[error("{0}")]
+    TokenRejected(String),
+    /// The provider refused the request until its rate limit resets; carries
+    /// the message saying when
+    #[error("{0}")]
+    RateLimited(String),
+    /// Interrupted with Ctrl-C while waiting for the user
+    #[error("Login cancelled")]
+    UserCancelled,
+    /// The cancellation token passed to
+    /// [`authenticate`](super::authenticate) was cancelled
+    #[error("Login cancelled by the application")]
+    Cancelled,
+}
+
+impl AuthError {
+    /// Process exit status for this failure, per the table above. 130 is what
+    /// a shell reports for a command ended by Ctrl-C.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::ConfigMissing(_) => 3,
+            Self::NetworkError(_) => 4,
+            Self::StateMismatch(_) => 5,
+            Self::Timeout(_) => 6,
+            Self::NotAuthenticated => 7,
+            Self::TokenRejected(_) => 8,
+            Self::AccessDenied => 9,
+            Self::DeviceCodeExpired => 10,
+            Self::RateLimited(_) => 11,
+            Self::Cancelled => 12,
+            Self::UserCancelled => 130,
+        }
+    }
+}
+
+/// Exit status for a failed command: the [`AuthError`] anywhere in its
+/// chain, else 1.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<AuthError>())
+        .map_or(1, AuthError::exit_code)
+}
+
+/// Put `prefix` in front of `err`'s message without losing a network error's
+/// exit status.
+pub fn with_prefix(err: anyhow::Error, prefix: &str) -> anyhow::Error {
+    match err.downcast::<AuthError>() {
+        Ok(AuthError::NetworkError(message)) => {
+            AuthError::NetworkError(format!("{}: {}", prefix, message)).into()
+        }
+        Ok(other) => anyhow::anyhow!("{}: {}", prefix, other),
+        Err(err) => anyhow::anyhow!("{}: {}", prefix, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_follows_the_chain() {
+        let err = anyhow::Error::from(AuthError::StateMismatch("OAuth callback"));
+        assert_eq!(exit_code(&err), 5);
+        let err = Err::<(), _>(AuthError::Timeout(60))
+            .context("login failed")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), 6);
+        assert_eq!(exit_code(&anyhow::anyhow!("boom")), 1);
+    }
+
+    #[test]
+    fn test_prefix_keeps_network_kind() {
+        let err = with_prefix(
+            AuthError::NetworkError("Request to x failed: reset".into()).into(),
+            "Token exchange failed",
+        );
+        assert_eq!(
+            err.to_string(),
+            "Token exchange failed: Request to x failed: reset"
+        );
+        assert_eq!(exit_code(&err), 4);
+        let err = with_prefix(anyhow::anyhow!("HTTP 500"), "Token exchange failed");
+        assert_eq!(exit_code(&err), 1);
+    }
+}
@@ -0,0 +1,924 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+use super::redact;
+use super::AuthError;
+
+/// Status, headers and body of a completed request.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// First value of a header, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn json(&self) -> Result<Value> {
+        parse_json(&self.body).map_err(|e| anyhow!("Failed to parse response as JSON: {}", e))
+    }
+
+    /// Whether the server sent an HTML page, by content type or by the look of
+    /// the body, as GitHub does for some misconfigured token requests.
+    pub fn is_html(&self) -> bool {
+        self.header("content-type")
+            .is_some_and(|value| value.to_ascii_lowercase().contains("text/html"))
+            || self
+                .body
+                .trim_start_matches('\u{feff}')
+                .trim_start()
+                .starts_with('<')
+    }
+
+    /// The quota headers, when the server sent any.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        let number = |name| self.header(name).and_then(|v| v.trim().parse::<u64>().ok());
+        let limit = RateLimit {
+            limit: number("x-ratelimit-limit"),
+            remaining: number("x-ratelimit-remaining"),
+            reset: number("x-ratelimit-reset")
+                .and_then(|secs| DateTime::from_timestamp(secs as i64, 0)),
+            retry_after: number("retry-after").map(Duration::from_secs),
+        };
+        (limit != RateLimit::default()).then_some(limit)
+    }
+
+    /// The quota, when this is a 403 or 429 that the quota explains. GitHub
+    /// also answers 403 for missing permissions, which has no such headers.
+    pub fn rate_limited(&self) -> Option<RateLimit> {
+        if !matches!(self.status, 403 | 429) {
+            return None;
+        }
+        self.rate_limit()
+            .filter(|limit| limit.remaining == Some(0) || limit.retry_after.is_some())
+    }
+}
+
+/// What `X-RateLimit-*` and `Retry-After` say about the request quota.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    /// When the quota refills
+    pub reset: Option<DateTime<Utc>>,
+    /// Only whole seconds; GitHub never sends the HTTP-date form
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimit {
+    /// How long to hold off before asking again, if the headers say.
+    pub fn wait(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.retry_after
+            .or_else(|| Some((self.reset? - now).to_std().unwrap_or_default()))
+    }
+
+    /// The error for a request refused by this quota.
+    pub fn error(&self, url: &str) -> anyhow::Error {
+        let message = match (self.reset, self.retry_after) {
+            (Some(reset), _) => format!(
+                "Rate limit exceeded at {}; it resets at {}",
+                url,
+                reset.to_rfc3339()
+            ),
+            (None, Some(retry_after)) => format!(
+                "Rate limit exceeded at {}; retry in {}s",
+                url,
+                retry_after.as_secs()
+            ),
+            (None, None) => format!("Rate limit exceeded at {}", url),
+        };
+        AuthError::RateLimited(message).into()
+    }
+}
+
+/// Parse a JSON body, tolerating the UTF-8 byte order mark and surrounding
+/// whitespace that some proxies add to otherwise valid responses.
+pub fn parse_json(body: &str) -> serde_json::Result<Value> {
+    serde_json::from_str(body.trim_start().trim_start_matches('\u{feff}').trim())
+}
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_MAX_RESPONSE_BYTES: u64 = 1024 * 1024;
+const DEFAULT_USER_AGENT: &str = concat!("goose-cli/", env!("CARGO_PKG_VERSION"));
+
+/// Which implementation sends requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// reqwest; the default when built with the `native-http` feature
+    #[cfg(feature = "native-http")]
+    Native,
+    /// The system `curl` binary, kept for setups that depend on its behavior
+    Curl,
+}
+
+/// `GOOSE_HTTP_BACKEND=native|curl`
+fn backend_from_env() -> Result<Backend> {
+    let backend = non_empty_env("GOOSE_HTTP_BACKEND").map(|backend| backend.trim().to_lowercase());
+    match backend.as_deref() {
+        Some("curl") => Ok(Backend::Curl),
+        #[cfg(feature = "native-http")]
+        None | Some("native") => Ok(Backend::Native),
+        #[cfg(not(feature = "native-http"))]
+        None => Ok(Backend::Curl),
+        #[cfg(not(feature = "native-http"))]
+        Some("native") => Err(anyhow!(
+            "GOOSE_HTTP_BACKEND=native needs goose built with the native-http feature"
+        )),
+        Some(other) => Err(anyhow!(
+            "Unknown GOOSE_HTTP_BACKEND '{}', expected native or curl",
+            other
+        )),
+    }
+}
+
+/// Transport settings shared by every auth request: the token exchange,
+/// identity validation, device flow, app tokens and JWKS fetches all go
+/// through one of these, so they cannot disagree about proxies or TLS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpClient {
+    pub backend: Backend,
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    pub user_agent: String,
+    /// `GOOSE_AUTH_PROXY`, used for every request ahead of the standard
+    /// variables. Without it curl reads `HTTPS_PROXY`/`HTTP_PROXY` itself,
+    /// while the native client is handed them by [`env_proxies`].
+    pub proxy: Option<String>,
+    /// `NO_PROXY`: hosts reached directly, also when `GOOSE_AUTH_PROXY` is set
+    pub no_proxy: Option<String>,
+    /// PEM bundle of additional trusted roots
+    pub ca_bundle: Option<PathBuf>,
+    /// Skip TLS certificate verification (debugging only)
+    pub insecure: bool,
+    pub max_response_bytes: u64,
+}
+
+/// Read the transport settings from the environment:
+///
+/// - `GOOSE_AUTH_HTTP_TIMEOUT_SECS` (default 30) and
+///   `GOOSE_AUTH_CONNECT_TIMEOUT_SECS` (default 10)
+/// - `GOOSE_AUTH_USER_AGENT` (default `goose-cli/<version>`)
+/// - `GOOSE_AUTH_PROXY`, else `HTTPS_PROXY`/`HTTP_PROXY`, with `NO_PROXY`
+/// - `GOOSE_AUTH_CA_BUNDLE`
+/// - `GOOSE_AUTH_INSECURE_SKIP_TLS_VERIFY=1`
+/// - `GOOSE_AUTH_MAX_RESPONSE_BYTES` (default 1 MiB)
+/// - `GOOSE_HTTP_BACKEND` (`native` by default, or `curl`)
+pub fn build_http_client() -> Result<HttpClient> {
+    let ca_bundle = non_empty_env("GOOSE_AUTH_CA_BUNDLE").map(|path| PathBuf::from(path.trim()));
+    if let Some(path) = &ca_bundle {
+        check_ca_bundle(path)?;
+    }
+    let insecure = std::env::var("GOOSE_AUTH_INSECURE_SKIP_TLS_VERIFY").unwrap_or_default() == "1";
+    if insecure {
+        eprintln!("[oauth-info] TLS certificate verification is disabled (GOOSE_AUTH_INSECURE_SKIP_TLS_VERIFY=1)");
+    }
+    let max_response_bytes = match non_empty_env("GOOSE_AUTH_MAX_RESPONSE_BYTES") {
+        Some(raw) => raw
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                anyhow!(
+                    "GOOSE_AUTH_MAX_RESPONSE_BYTES must be a positive integer, got '{}'",
+                    raw
+                )
+            })?,
+        None => DEFAULT_MAX_RESPONSE_BYTES,
+    };
+    Ok(HttpClient {
+        backend: backend_from_env()?,
+        timeout: super::env_secs("GOOSE_AUTH_HTTP_TIMEOUT_SECS", DEFAULT_TIMEOUT_SECS)?,
+        connect_timeout: super::env_secs(
+            "GOOSE_AUTH_CONNECT_TIMEOUT_SECS",
+            DEFAULT_CONNECT_TIMEOUT_SECS,
+        )?,
+        user_agent: non_empty_env("GOOSE_AUTH_USER_AGENT")
+            .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+        proxy: non_empty_env("GOOSE_AUTH_PROXY"),
+        no_proxy: first_env(&["NO_PROXY", "no_proxy"]),
+        ca_bundle,
+        insecure,
+        max_response_bytes,
+    })
+}
+
+fn non_empty_env(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// Make sure `GOOSE_AUTH_CA_BUNDLE` holds at least one well-formed PEM
+/// certificate. Checked up front because both backends otherwise report a bad
+/// bundle as an unhelpful handshake failure, or silently ignore it.
+fn check_ca_bundle(path: &Path) -> Result<()> {
+    let pem = std::fs::read_to_string(path).map_err(|e| {
+        anyhow!(
+            "GOOSE_AUTH_CA_BUNDLE points to {}, which cannot be read: {}",
+            path.display(),
+            e
+        )
+    })?;
+    let invalid = |reason: &str| {
+        anyhow!(
+            "GOOSE_AUTH_CA_BUNDLE points to {}, which is not a valid PEM bundle: {}",
+            path.display(),
+            reason
+        )
+    };
+    let mut certificates = 0;
+    let mut body: Option<String> = None;
+    for line in pem.lines().map(str::trim) {
+        match (line, body.as_mut()) {
+            (PEM_BEGIN, None) => body = Some(String::new()),
+            (PEM_BEGIN, Some(_)) => return Err(invalid("certificate is missing its END line")),
+            (PEM_END, Some(encoded)) => {
+                STANDARD.decode(encoded.as_bytes()).map_err(|_| {
+                    invalid(&format!("certificate {} is not base64", certificates + 1))
+                })?;
+                certificates += 1;
+                body = None;
+            }
+            (line, Some(encoded)) => encoded.push_str(line),
+            _ => {}
+        }
+    }
+    if body.is_some() {
+        return Err(invalid("certificate is missing its END line"));
+    }
+    if certificates == 0 {
+        return Err(invalid("no certificates found"));
+    }
+    Ok(())
+}
+
+fn first_env(names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| non_empty_env(name))
+}
+
+/// The standard proxy variables for the native client. reqwest only reads
+/// them when no proxy is configured at all, so they are passed explicitly.
+#[cfg(feature = "native-http")]
+fn env_proxies(no_proxy: Option<&str>) -> Result<Vec<reqwest::Proxy>> {
+    let mut proxies = Vec::new();
+    for (names, https) in [
+        (["HTTPS_PROXY", "https_proxy"], true),
+        (["HTTP_PROXY", "http_proxy"], false),
+    ] {
+        let Some(url) = first_env(&names) else {
+            continue;
+        };
+        let proxy = if https {
+            reqwest::Proxy::https(&url)
+        } else {
+            reqwest::Proxy::http(&url)
+        }
+        .map_err(|e| anyhow!("Invalid {} '{}': {}", names[0], url, e))?;
+        proxies.push(proxy.no_proxy(no_proxy.and_then(reqwest::NoProxy::from_string)));
+    }
+    Ok(proxies)
+}
+
+enum Auth<'a> {
+    Bearer(&'a str),
+    /// Client id and secret
+    Basic(&'a str, &'a str),
+}
+
+impl Auth<'_> {
+    fn header_value(&self) -> String {
+        match self {
+            Auth::Bearer(token) => format!("Bearer {}", token),
+            Auth::Basic(user, password) => {
+                format!(
+                    "Basic {}",
+                    STANDARD.encode(format!("{}:{}", user, password))
+                )
+            }
+        }
+    }
+}
+
+enum Body<'a> {
+    Form(&'a [(&'a str, &'a str)]),
+    Json(&'a Value),
+}
+
+struct Request<'a> {
+    method: &'static str,
+    url: &'a str,
+    auth: Option<Auth<'a>>,
+    body: Option<Body<'a>>,
+}
+
+impl HttpClient {
+    /// POST an `application/x-www-form-urlencoded` body, asking for JSON back.
+    pub async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<HttpResponse> {
+        self.send(Request {
+            method: "POST",
+            url,
+            auth: None,
+            body: Some(Body::Form(form)),
+        })
+        .await
+    }
+
+    /// POST a form authenticated as an OAuth client with HTTP Basic.
+    pub async fn post_form_as_client(
+        &self,
+        url: &str,
+        client: (&str, &str),
+        form: &[(&str, &str)],
+    ) -> Result<HttpResponse> {
+        self.send(Request {
+            method: "POST",
+            url,
+            auth: Some(Auth::Basic(client.0, client.1)),
+            body: Some(Body::Form(form)),
+        })
+        .await
+    }
+
+    /// GET a JSON resource, optionally with a bearer token.
+    pub async fn get(&self, url: &str, bearer: Option<&str>) -> Result<HttpResponse> {
+        self.send(Request {
+            method: "GET",
+            url,
+            auth: bearer.map(Auth::Bearer),
+            body: None,
+        })
+        .await
+    }
+
+    /// HEAD, to see whether an endpoint answers at all.
+    pub async fn head(&self, url: &str) -> Result<HttpResponse> {
+        self.send(Request {
+            method: "HEAD",
+            url,
+            auth: None,
+            body: None,
+        })
+        .await
+    }
+
+    /// POST without a body, optionally with a bearer token.
+    pub async fn post(&self, url: &str, bearer: Option<&str>) -> Result<HttpResponse> {
+        self.send(Request {
+            method: "POST",
+            url,
+            auth: bearer.map(Auth::Bearer),
+            body: None,
+        })
+        .await
+    }
+
+    /// DELETE with a JSON body, authenticated as an OAuth client.
+    pub async fn delete_json(
+        &self,
+        url: &str,
+        client: (&str, &str),
+        body: &Value,
+    ) -> Result<HttpResponse> {
+        self.send(Request {
+            method: "DELETE",
+            url,
+            auth: Some(Auth::Basic(client.0, client.1)),
+            body: Some(Body::Json(body)),
+        })
+        .await
+    }
+
+    async fn send(&self, request: Request<'_>) -> Result<HttpResponse> {
+        match self.backend {
+            #[cfg(feature = "native-http")]
+            Backend::Native => self.send_native(&request).await,
+            Backend::Curl => self.send_curl(&request).await,
+        }
+    }
+
+    fn too_large(&self, url: &str) -> anyhow::Error {
+        anyhow!(
+            "Response from {} exceeds {} bytes",
+            url,
+            self.max_response_bytes
+        )
+    }
+
+    #[cfg(feature = "native-http")]
+    fn native_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .user_agent(&self.user_agent)
+            .danger_accept_invalid_certs(self.insecure);
+        let no_proxy = self.no_proxy.as_deref();
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| anyhow!("Invalid GOOSE_AUTH_PROXY '{}': {}", proxy, e))?
+                .no_proxy(no_proxy.and_then(reqwest::NoProxy::from_string));
+            builder = builder.proxy(proxy);
+        } else {
+            for proxy in env_proxies(no_proxy)? {
+                builder = builder.proxy(proxy);
+            }
+        }
+        if let Some(path) = &self.ca_bundle {
+            let pem = std::fs::read(path)
+                .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| {
+                anyhow!(
+                    "GOOSE_AUTH_CA_BUNDLE points to {}, which is not a valid PEM bundle: {}",
+                    path.display(),
+                    e
+                )
+            })?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        builder
+            .build()
+            .map_err(|e| anyhow!("Failed to build HTTP client: {}", e))
+    }
+
+    #[cfg(feature = "native-http")]
+    async fn send_native(&self, request: &Request<'_>) -> Result<HttpResponse> {
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())?;
+        let mut builder = self
+            .native_client()?
+            .request(method, request.url)
+            .header(reqwest::header::ACCEPT, "application/json");
+        if let Some(auth) = &request.auth {
+            builder = builder.header(reqwest::header::AUTHORIZATION, auth.header_value());
+        }
+        match request.body {
+            Some(Body::Form(form)) => builder = builder.form(form),
+            Some(Body::Json(json)) => {
+                builder = builder
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(serde_json::to_vec(json)?)
+            }
+            None => {}
+        }
+        let mut response = builder.send().await.map_err(|e| {
+            AuthError::NetworkError(format!("Request to {} failed: {}", request.url, e))
+        })?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(key, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (key.as_str().to_string(), value)
+            })
+            .collect();
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| {
+            AuthError::NetworkError(format!("Request to {} failed: {}", request.url, e))
+        })? {
+            body.extend_from_slice(&chunk);
+            if body.len() as u64 > self.max_response_bytes {
+                return Err(self.too_large(request.url));
+            }
+        }
+        Ok(HttpResponse {
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    fn base_args(&self, method: &str) -> Vec<String> {
+        let mut args: Vec<String> = vec![
+            "-s".into(),
+            "-S".into(),
+            "-i".into(),
+            "-H".into(),
+            "Accept: application/json".into(),
+            "-A".into(),
+            self.user_agent.clone(),
+            "--max-time".into(),
+            self.timeout.as_secs().to_string(),
+            "--connect-timeout".into(),
+            self.connect_timeout.as_secs().to_string(),
+            "--max-filesize".into(),
+            self.max_response_bytes.to_string(),
+        ];
+        // `-X HEAD` would leave curl waiting for a body that never comes
+        if method == "HEAD" {
+            args.push("--head".into());
+        } else {
+            args.push("-X".into());
+            args.push(method.into());
+        }
+        // curl applies the proxy variables on its own; only the override and
+        // its exceptions need passing
+        if let Some(proxy) = &self.proxy {
+            args.push("--proxy".into());
+            args.push(proxy.clone());
+            if let Some(no_proxy) = &self.no_proxy {
+                args.push("--noproxy".into());
+                args.push(no_proxy.clone());
+            }
+        }
+        if let Some(ca_bundle) = &self.ca_bundle {
+            args.push("--cacert".into());
+            args.push(ca_bundle.display().to_string());
+        }
+        if self.insecure {
+            args.push("-k".into());
+        }
+        args
+    }
+
+    async fn send_curl(&self, request: &Request<'_>) -> Result<HttpResponse> {
+        let mut args = self.base_args(request.method);
+        if let Some(auth) = &request.auth {
+            args.push("-H".into());
+            args.push(format!("Authorization: {}", auth.header_value()));
+        }
+        match request.body {
+            Some(Body::Form(form)) => {
+                args.push("-H".into());
+                args.push("Content-Type: application/x-www-form-urlencoded".into());
+                for (key, value) in form {
+                    args.push("--data-urlencode".into());
+                    args.push(format!("{}={}", key, value));
+                }
+            }
+            Some(Body::Json(json)) => {
+                args.push("-H".into());
+                args.push("Content-Type: application/json".into());
+                args.push("--data-binary".into());
+                args.push(json.to_string());
+            }
+            None => {}
+        }
+        args.push(request.url.into());
+
+        let output = Command::new("curl")
+            .args(&args)
+            .output()
+            .await
+            .map_err(curl_spawn_error)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(AuthError::NetworkError(format!(
+                "Request to {} failed: {}",
+                request.url,
+                redact::redact(stderr.trim())
+            ))
+            .into());
+        }
+        let response = parse_response(&String::from_utf8_lossy(&output.stdout))?;
+        if response.body.len() as u64 > self.max_response_bytes {
+            return Err(self.too_large(request.url));
+        }
+        Ok(response)
+    }
+}
+
+/// The first line of `curl --version`, to check that the curl backend can run
+/// at all before a login depends on it.
+pub async fn curl_version() -> Result<String> {
+    let output = Command::new("curl")
+        .arg("--version")
+        .output()
+        .await
+        .map_err(curl_spawn_error)?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .unwrap_or("curl")
+        .to_string())
+}
+
+// A missing binary is the common case, and the OS error alone ("No such file
+// or directory") does not say which file.
+fn curl_spawn_error(e: std::io::Error) -> anyhow::Error {
+    if e.kind() != std::io::ErrorKind::NotFound {
+        return anyhow!("Failed to run curl: {}", e);
+    }
+    #[cfg(feature = "native-http")]
+    let fix = "install curl, or unset GOOSE_HTTP_BACKEND=curl to use the built-in client";
+    #[cfg(not(feature = "native-http"))]
+    let fix = "install curl, or build goose with the native-http feature so it does not need curl";
+    anyhow!("curl was not found on PATH; {}", fix)
+}
+
+/// Copy-pasteable curl command equivalent to [`HttpClient::post_form`], for
+/// debugging whichever backend actually sent it.
+/// Sensitive form values are masked unless `show_secrets` is set.
+pub fn curl_command(url: &str, form: &[(&str, &str)], show_secrets: bool) -> String {
+    curl_command_masking(url, form, |key| !show_secrets && redact::is_sensitive(key))
+}
+
+/// The same request, meant to be run on another machine: the one-time code
+/// and verifier are filled in so it works as printed, and only the client
+/// secret is masked unless `show_secrets` is set.
+pub fn offline_curl_command(url: &str, form: &[(&str, &str)], show_secrets: bool) -> String {
+    curl_command_masking(url, form, |key| !show_secrets && key == "client_secret")
+}
+
+fn curl_command_masking(url: &str, form: &[(&str, &str)], mask: impl Fn(&str) -> bool) -> String {
+    let mut args: Vec<String> = vec![
+        "curl".into(),
+        "-sS".into(),
+        "-X".into(),
+        "POST".into(),
+        "-H".into(),
+        "Accept: application/json".into(),
+        "-H".into(),
+        "Content-Type: application/x-www-form-urlencoded".into(),
+    ];
+    for (key, value) in form {
+        let value = if mask(key) { redact::REDACTED } else { value };
+        args.push("--data-urlencode".into());
+        args.push(format!("{}={}", key, value));
+    }
+    args.push(url.into());
+    args.iter()
+        .map(|arg| shlex::try_quote(arg).map_or_else(|_| arg.clone(), |quoted| quoted.into_owned()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// `curl -i` prints every header block it saw (e.g. `100 Continue` or a proxy's
+// `200 Connection established`) before the final one; keep only the last.
+fn parse_response(raw: &str) -> Result<HttpResponse> {
+    let mut rest = raw;
+    let mut head = "";
+    while rest.starts_with("HTTP/") {
+        match rest.find("\r\n\r\n") {
+            Some(end) => {
+                head = &rest[..end];
+                rest = &rest[end + 4..];
+            }
+            None => {
+                head = rest;
+                rest = "";
+            }
+        }
+    }
+
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| anyhow!("Malformed HTTP response"))?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    Ok(HttpResponse {
+        status,
+        headers,
+        body: rest.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response_skips_interim_blocks() {
+        let raw = "HTTP/1.1 100 Continue\r\n\r\nHTTP/2 201 \r\nContent-Type: application/json\r\nX-OAuth-Scopes: repo, gist\r\n\r\n{\"ok\":true}";
+        let response = parse_response(raw).unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(response.header("x-oauth-scopes"), Some("repo, gist"));
+        assert_eq!(response.json().unwrap()["ok"], true);
+    }
+
+    #[test]
+    fn test_bom_prefixed_token_response_parses() {
+        let raw = "HTTP/2 200 \r\nContent-Type: application/json\r\n\r\n\u{feff}  {\"access_token\":\"gho_abc\",\"token_type\":\"bearer\"}\r\n";
+        let json = parse_response(raw).unwrap().json().unwrap();
+        assert_eq!(json["access_token"], "gho_abc");
+        assert!(parse_json("\n\u{feff}{\"scope\":\"repo\"}  ").is_ok());
+    }
+
+    #[test]
+    fn test_curl_command_masks_secrets_by_default() {
+        let form = [
+            ("client_id", "Iv1.abc"),
+            ("client_secret", "shh-secret"),
+            ("code", "one-time-code"),
+            ("grant_type", "authorization_code"),
+        ];
+        let url = "https://github.com/login/oauth/access_token";
+        let masked = curl_command(url, &form, false);
+        assert!(masked.starts_with("curl "));
+        assert!(masked.contains("client_id=Iv1.abc"));
+        assert!(masked.contains("'client_secret=<redacted>'"));
+        assert!(!masked.contains("shh-secret"));
+        assert!(!masked.contains("one-time-code"));
+
+        let full = curl_command(url, &form, true);
+        assert!(full.contains("client_secret=shh-secret"));
+        assert!(full.contains("code=one-time-code"));
+    }
+
+    #[test]
+    fn test_client_carries_timeout_and_user_agent() {
+        temp_env::with_vars(
+            [
+                ("GOOSE_AUTH_HTTP_TIMEOUT_SECS", Some("7")),
+                ("GOOSE_AUTH_USER_AGENT", Some("ci-bot/1.0")),
+                ("GOOSE_AUTH_PROXY", None),
+            ],
+            || {
+                let client = build_http_client().unwrap();
+                assert_eq!(client.timeout, Duration::from_secs(7));
+                assert_eq!(client.user_agent, "ci-bot/1.0");
+
+                let args = client.base_args("GET");
+                let after = |flag: &str| {
+                    let i = args.iter().position(|arg| arg == flag).unwrap();
+                    args[i + 1].clone()
+                };
+                assert_eq!(after("--max-time"), "7");
+                assert_eq!(after("-A"), "ci-bot/1.0");
+                assert!(!args.contains(&"--proxy".to_string()));
+            },
+        );
+    }
+
+    #[cfg(feature = "native-http")]
+    #[tokio::test]
+    async fn test_native_post_form_sends_json_and_form_headers() {
+        use wiremock::matchers::{body_string_contains, header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("accept", "application/json"))
+            .and(header("content-type", "application/x-www-form-urlencoded"))
+            .and(body_string_contains("code=abc+123"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"access_token": "t"})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = HttpClient {
+            backend: Backend::Native,
+            ..build_http_client().unwrap()
+        };
+        let response = client
+            .post_form(&server.uri(), &[("code", "abc 123")])
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.json().unwrap()["access_token"], "t");
+    }
+
+    #[cfg(feature = "native-http")]
+    #[tokio::test]
+    async fn test_native_client_sends_through_goose_auth_proxy() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        // A plain-HTTP proxy receives the request for the target host as-is,
+        // so the mock only answers if the request was routed through it
+        let proxy = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/login/oauth/access_token"))
+            .and(header("host", "github.invalid"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"access_token": "t"})),
+            )
+            .expect(1)
+            .mount(&proxy)
+            .await;
+
+        let client = HttpClient {
+            backend: Backend::Native,
+            proxy: Some(proxy.uri()),
+            no_proxy: None,
+            ..build_http_client().unwrap()
+        };
+        let response = client
+            .post_form("http://github.invalid/login/oauth/access_token", &[])
+            .await
+            .unwrap();
+        assert_eq!(response.json().unwrap()["access_token"], "t");
+    }
+
+    #[test]
+    fn test_offline_curl_command_masks_only_the_secret() {
+        let form = [
+            ("client_secret", "shh-secret"),
+            ("code", "one-time-code"),
+            ("code_verifier", "verifier"),
+        ];
+        let command = offline_curl_command("https://github.com/token", &form, false);
+        assert!(command.contains("'client_secret=<redacted>'"));
+        assert!(command.contains("code=one-time-code"));
+        assert!(command.contains("code_verifier=verifier"));
+    }
+
+    #[test]
+    fn test_missing_curl_says_what_to_do() {
+        let err = curl_spawn_error(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(err
+            .to_string()
+            .starts_with("curl was not found on PATH; install curl"));
+        let err = curl_spawn_error(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert!(err.to_string().starts_with("Failed to run curl"));
+    }
+
+    #[test]
+    fn test_curl_passes_no_proxy_with_override() {
+        let client = HttpClient {
+            backend: Backend::Curl,
+            proxy: Some("http://proxy.corp:3128".to_string()),
+            no_proxy: Some("localhost,.corp".to_string()),
+            ..build_http_client().unwrap()
+        };
+        let args = client.base_args("POST");
+        let i = args.iter().position(|arg| arg == "--noproxy").unwrap();
+        assert_eq!(args[i + 1], "localhost,.corp");
+    }
+
+    #[test]
+    fn test_bad_ca_bundle_is_reported_up_front() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.pem");
+        let err = check_ca_bundle(&missing).unwrap_err();
+        assert!(err.to_string().contains("cannot be read"));
+
+        let garbage = dir.path().join("garbage.pem");
+        std::fs::write(&garbage, "not a certificate\n").unwrap();
+        let err = check_ca_bundle(&garbage).unwrap_err();
+        assert!(err.to_string().contains("no certificates found"));
+
+        let truncated = dir.path().join("truncated.pem");
+        std::fs::write(&truncated, format!("{}\nMIIB\n", PEM_BEGIN)).unwrap();
+        assert!(check_ca_bundle(&truncated).is_err());
+
+        let bundle = dir.path().join("bundle.pem");
+        std::fs::write(&bundle, format!("{}\nTUlJQg==\n{}\n", PEM_BEGIN, PEM_END)).unwrap();
+        check_ca_bundle(&bundle).unwrap();
+    }
+
+    #[test]
+    fn test_rate_limited_403_reports_reset() {
+        let response = HttpResponse {
+            status: 403,
+            headers: vec![
+                ("X-RateLimit-Limit".into(), "60".into()),
+                ("X-RateLimit-Remaining".into(), "0".into()),
+                ("X-RateLimit-Reset".into(), "1700000000".into()),
+            ],
+            body: String::new(),
+        };
+        let limit = response.rate_limited().unwrap();
+        assert_eq!(limit.remaining, Some(0));
+        let err = limit.error("https://api.github.com/user");
+        assert_eq!(
+            err.to_string(),
+            "Rate limit exceeded at https://api.github.com/user; it resets at 2023-11-14T22:13:20+00:00"
+        );
+        assert!(matches!(
+            err.downcast_ref::<AuthError>(),
+            Some(AuthError::RateLimited(_))
+        ));
+
+        let forbidden = HttpResponse {
+            headers: vec![("X-RateLimit-Remaining".into(), "59".into())],
+            ..response
+        };
+        assert!(forbidden.rate_limited().is_none());
+        assert_eq!(forbidden.rate_limit().unwrap().remaining, Some(59));
+    }
+
+    #[test]
+    fn test_parse_response_rejects_garbage() {
+        assert!(parse_response("not http").is_err());
+    }
+}
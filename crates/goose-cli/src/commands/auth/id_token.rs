@@ -0,0 +1,181 @@
+//! id_token verification for OIDC logins (OpenID Connect Core 3.1.3.7).
+//!
+//! The authorize request carries a random `nonce`; the id_token in the token
+//! response must echo it, name the discovered issuer and our client id as
+//! `iss` and `aud`, not have expired, and be signed with a key from the
+//! issuer's JWKS. Tokens signed with HS256/384/512 are checked against the
+//! client secret instead, as the spec requires.
+
+use anyhow::{anyhow, Result};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::jwks::JwksCache;
+
+/// Where an issuer's id_tokens come from and who signs them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdTokenIssuer {
+    /// `iss` every id_token must carry
+    pub issuer: String,
+    /// Signing keys, from the discovery document
+    pub jwks_uri: Option<String>,
+}
+
+/// What the login expects the id_token to match.
+pub struct IdTokenCheck<'a> {
+    pub issuer: &'a IdTokenIssuer,
+    /// Sent in the authorize request
+    pub nonce: &'a str,
+}
+
+/// Claims of a verified id_token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub exp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Everything else the issuer put in, e.g. `email` or `name`
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// Verify `id_token` for `client_id` and return its claims.
+pub async fn verify(
+    id_token: &str,
+    check: &IdTokenCheck<'_>,
+    client_id: &str,
+    client_secret: Option<&str>,
+) -> Result<IdTokenClaims> {
+    let header =
+        decode_header(id_token).map_err(|e| anyhow!("The id_token is malformed: {}", e))?;
+    let key = if is_symmetric(header.alg) {
+        let secret = client_secret.ok_or_else(|| {
+            anyhow!(
+                "The id_token is signed with {:?}, which needs the client secret to verify",
+                header.alg
+            )
+        })?;
+        DecodingKey::from_secret(secret.as_bytes())
+    } else {
+        let jwks_uri = check.issuer.jwks_uri.as_deref().ok_or_else(|| {
+            anyhow!(
+                "Cannot verify the id_token: the discovery document for {} has no jwks_uri",
+                check.issuer.issuer
+            )
+        })?;
+        let kid = header
+            .kid
+            .as_deref()
+            .ok_or_else(|| anyhow!("The id_token header names no signing key (kid)"))?;
+        JwksCache::open(jwks_uri)?.decoding_key(kid).await?
+    };
+    verify_with_key(id_token, &key, header.alg, check, client_id)
+}
+
+fn is_symmetric(alg: Algorithm) -> bool {
+    matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn verify_with_key(
+    id_token: &str,
+    key: &DecodingKey,
+    alg: Algorithm,
+    check: &IdTokenCheck<'_>,
+    client_id: &str,
+) -> Result<IdTokenClaims> {
+    let mut validation = Validation::new(alg);
+    validation.set_issuer(&[check.issuer.issuer.as_str()]);
+    validation.set_audience(&[client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    let claims = decode::<IdTokenClaims>(id_token, key, &validation)
+        .map_err(|e| anyhow!("The id_token failed verification: {}", e))?
+        .claims;
+    match claims.nonce.as_deref() {
+        Some(nonce) if super::state_matches(nonce, check.nonce) => Ok(claims),
+        Some(_) => Err(anyhow!(
+            "The id_token nonce does not match the one sent with the login"
+        )),
+        None => Err(anyhow!("The id_token carries no nonce")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    const ISSUER: &str = "https://login.example.com";
+    const SECRET: &str = "client-secret";
+
+    fn sign(claims: Value) -> String {
+        encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(SECRET.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn claims(nonce: &str) -> Value {
+        json!({
+            "iss": ISSUER,
+            "aud": "client",
+            "sub": "user-1",
+            "exp": chrono::Utc::now().timestamp() + 300,
+            "nonce": nonce,
+            "email": "user@example.com",
+        })
+    }
+
+    fn check(issuer: &IdTokenIssuer) -> IdTokenCheck<'_> {
+        IdTokenCheck {
+            issuer,
+            nonce: "expected-nonce",
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verified_claims_are_returned() {
+        let issuer = IdTokenIssuer {
+            issuer: ISSUER.to_string(),
+            jwks_uri: None,
+        };
+        let token = sign(claims("expected-nonce"));
+        let verified = verify(&token, &check(&issuer), "client", Some(SECRET))
+            .await
+            .unwrap();
+        assert_eq!(verified.sub, "user-1");
+        assert_eq!(verified.other["email"], "user@example.com");
+
+        // Without the secret an HMAC-signed token cannot be checked
+        assert!(verify(&token, &check(&issuer), "client", None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_claims_are_rejected() {
+        let issuer = IdTokenIssuer {
+            issuer: ISSUER.to_string(),
+            jwks_uri: None,
+        };
+        let mut wrong_issuer = claims("expected-nonce");
+        wrong_issuer["iss"] = json!("https://evil.example.com");
+        let mut expired = claims("expected-nonce");
+        expired["exp"] = json!(chrono::Utc::now().timestamp() - 3600);
+        for (token, client_id) in [
+            (sign(claims("replayed-nonce")), "client"),
+            (sign(claims("expected-nonce")), "other-client"),
+            (sign(wrong_issuer), "client"),
+            (sign(expired), "client"),
+        ] {
+            assert!(verify(&token, &check(&issuer), client_id, Some(SECRET))
+                .await
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use url::Url;
+
+use super::http;
+use super::provider::Provider;
+use super::scopes::normalize_scopes;
+use super::validation_cache;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenValidation {
+    /// The token works; carries the identity it belongs to and, when the API
+    /// reports them (GitHub's `X-OAuth-Scopes`), the scopes it grants
+    Valid {
+        login: String,
+        scopes: Option<Vec<String>>,
+        /// The API's request quota, when it reports one
+        rate_limit: Option<http::RateLimit>,
+        /// OAuth app the token was issued to (GitHub's `X-OAuth-Client-Id`)
+        client_id: Option<String>,
+    },
+    /// The API answered 401, so the token is expired or revoked
+    Invalid,
+}
+
+/// Identity endpoint and the JSON field naming the user.
+///
+/// Each provider has its own (GitHub's `/user`, GitLab's `/api/v4/user`);
+/// `GOOSE_AUTH_IDENTITY_URL` and `GOOSE_AUTH_IDENTITY_LOGIN_FIELD` (dots for
+/// nested fields) override them for self-hosted or non-GitHub bearer APIs.
+#[derive(Debug, Clone)]
+pub struct IdentityEndpoint {
+    pub url: Url,
+    pub login_field: String,
+}
+
+impl IdentityEndpoint {
+    pub fn for_provider(provider: &Provider) -> Result<Self> {
+        Self::new(&provider.user_url, &provider.login_field)
+    }
+
+    pub fn new(url: &str, login_field: &str) -> Result<Self> {
+        let url = Url::parse(url).map_err(|e| anyhow!("Invalid identity URL '{}': {}", url, e))?;
+        if url.scheme() != "https" && !is_loopback(&url) {
+            return Err(anyhow!(
+                "Identity URL must use https (plain http is only allowed for localhost): {}",
+                url
+            ));
+        }
+        Ok(Self {
+            url,
+            login_field: login_field.trim().to_string(),
+        })
+    }
+
+    fn extract_login(&self, json: &Value) -> Option<String> {
+        let value = self
+            .login_field
+            .split('.')
+            .try_fold(json, |value, key| value.get(key))?;
+        match value {
+            Value::String(s) if !s.is_empty() => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// `localhost` or a loopback address, which may be reached over plain http.
+pub(super) fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(url::Host::Domain(host)) => host.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+/// Check a token against `provider`'s identity endpoint, keeping the
+/// [`validation_cache`] in step with the answer.
+pub async fn validate_token(provider: &Provider, token: &str) -> Result<TokenValidation> {
+    let validation = validate_token_at(&IdentityEndpoint::for_provider(provider)?, token).await?;
+    match &validation {
+        TokenValidation::Valid { login, .. } => validation_cache::record(token, login),
+        TokenValidation::Invalid => validation_cache::forget(token),
+    }
+    Ok(validation)
+}
+
+pub async fn validate_token_at(
+    endpoint: &IdentityEndpoint,
+    token: &str,
+) -> Result<TokenValidation> {
+    let response = http::build_http_client()?
+        .get(endpoint.url.as_str(), Some(token))
+        .await?;
+    if response.status == 401 {
+        return Ok(TokenValidation::Invalid);
+    }
+    if let Some(limit) = response.rate_limited() {
+        return Err(limit.error(endpoint.url.as_str()));
+    }
+    if !response.is_success() {
+        return Err(anyhow!(
+            "Identity check at {} returned HTTP {}",
+            endpoint.url,
+            response.status
+        ));
+    }
+    let json = response.json()?;
+    let login = endpoint.extract_login(&json).ok_or_else(|| {
+        anyhow!(
+            "Identity response from {} has no '{}' field",
+            endpoint.url,
+            endpoint.login_field
+        )
+    })?;
+    let scopes = response.header("x-oauth-scopes").map(normalize_scopes);
+    Ok(TokenValidation::Valid {
+        login,
+        scopes,
+        rate_limit: response.rate_limit(),
+        client_id: response.header("x-oauth-client-id").map(str::to_string),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_identity_url_must_be_https() {
+        assert!(IdentityEndpoint::new("http://git.example.com/api/me", "login").is_err());
+        assert!(IdentityEndpoint::new("https://git.example.com/api/me", "login").is_ok());
+        assert!(IdentityEndpoint::new("http://127.0.0.1:9000/me", "login").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_custom_identity_field() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/me"))
+            .and(header("authorization", "Bearer test-token"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"data": {"username": "octocat"}, "id": 1})),
+            )
+            .mount(&server)
+            .await;
+
+        let endpoint =
+            IdentityEndpoint::new(&format!("{}/api/me", server.uri()), "data.username").unwrap();
+        assert_eq!(
+            validate_token_at(&endpoint, "test-token").await.unwrap(),
+            TokenValidation::Valid {
+                login: "octocat".to_string(),
+                scopes: None,
+                rate_limit: None,
+                client_id: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scopes_header_is_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-oauth-scopes", "read:user, user:email")
+                    .insert_header("x-oauth-client-id", "Iv1.abc")
+                    .set_body_json(json!({"login": "octocat"})),
+            )
+            .mount(&server)
+            .await;
+
+        let endpoint = IdentityEndpoint::new(&server.uri(), "login").unwrap();
+        let TokenValidation::Valid {
+            scopes, client_id, ..
+        } = validate_token_at(&endpoint, "test-token").await.unwrap()
+        else {
+            panic!("expected a valid token");
+        };
+        assert_eq!(scopes.unwrap(), ["read:user", "user:email"]);
+        assert_eq!(client_id.as_deref(), Some("Iv1.abc"));
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_token_is_invalid() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let endpoint = IdentityEndpoint::new(&server.uri(), "login").unwrap();
+        assert_eq!(
+            validate_token_at(&endpoint, "stale").await.unwrap(),
+            TokenValidation::Invalid
+        );
+    }
+}
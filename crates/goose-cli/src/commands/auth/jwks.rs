@@ -0,0 +1,202 @@
+//! On-disk JWKS cache for id_token signature verification.
+//!
+//! Key sets are cached per `jwks_uri` under the auth config dir for as long as
+//! the provider's `Cache-Control: max-age` allows (an hour when it says
+//! nothing). An id_token signed with a `kid` the cache does not know triggers
+//! one refetch before failing, so a provider's key rotation is picked up
+//! immediately instead of after the cache expires.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::DecodingKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use super::http;
+use super::token_store::{self, write_atomic};
+
+/// TTL used when the JWKS response carries no usable `max-age`
+const DEFAULT_TTL_SECS: i64 = 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedJwks {
+    jwks_uri: String,
+    expires_at: DateTime<Utc>,
+    keys: JwkSet,
+}
+
+pub struct JwksCache {
+    jwks_uri: String,
+    path: PathBuf,
+    cached: Option<CachedJwks>,
+}
+
+impl JwksCache {
+    /// Open the cache for `jwks_uri` in the default location.
+    pub fn open(jwks_uri: &str) -> Result<Self> {
+        Ok(Self::open_in(
+            &token_store::auth_dir()?.join("jwks"),
+            jwks_uri,
+        ))
+    }
+
+    /// Open the cache for `jwks_uri` stored in `dir`, loading any previous copy.
+    pub fn open_in(dir: &Path, jwks_uri: &str) -> Self {
+        let digest = Sha256::digest(jwks_uri.as_bytes());
+        let path = dir.join(format!("{}.json", URL_SAFE_NO_PAD.encode(&digest[..12])));
+        let cached = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<CachedJwks>(&contents).ok())
+            .filter(|cached| cached.jwks_uri == jwks_uri);
+        Self {
+            jwks_uri: jwks_uri.to_string(),
+            path,
+            cached,
+        }
+    }
+
+    /// Key for `kid`, fetching the JWKS when the cache is stale and refetching
+    /// once more when the key is unknown.
+    pub async fn decoding_key(&mut self, kid: &str) -> Result<DecodingKey> {
+        let mut refreshed = false;
+        if !self.is_fresh() {
+            self.refresh().await?;
+            refreshed = true;
+        }
+        if let Some(key) = self.find(kid)? {
+            return Ok(key);
+        }
+        if !refreshed {
+            self.refresh().await?;
+            if let Some(key) = self.find(kid)? {
+                return Ok(key);
+            }
+        }
+        Err(anyhow!(
+            "No signing key with kid '{}' in the JWKS at {}",
+            kid,
+            self.jwks_uri
+        ))
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.cached
+            .as_ref()
+            .is_some_and(|cached| cached.expires_at > Utc::now())
+    }
+
+    fn find(&self, kid: &str) -> Result<Option<DecodingKey>> {
+        let Some(jwk) = self
+            .cached
+            .as_ref()
+            .and_then(|cached| cached.keys.find(kid))
+        else {
+            return Ok(None);
+        };
+        DecodingKey::from_jwk(jwk)
+            .map(Some)
+            .map_err(|e| anyhow!("Unusable JWKS key '{}': {}", kid, e))
+    }
+
+    /// Fetch the key set and replace the cached copy.
+    pub async fn refresh(&mut self) -> Result<()> {
+        let response = http::build_http_client()?.get(&self.jwks_uri, None).await?;
+        if !response.is_success() {
+            return Err(anyhow!(
+                "JWKS fetch from {} returned HTTP {}",
+                self.jwks_uri,
+                response.status
+            ));
+        }
+        let keys: JwkSet = serde_json::from_value(response.json()?)
+            .map_err(|e| anyhow!("Invalid JWKS from {}: {}", self.jwks_uri, e))?;
+        let ttl = response
+            .header("cache-control")
+            .and_then(max_age)
+            .unwrap_or(DEFAULT_TTL_SECS);
+        let cached = CachedJwks {
+            jwks_uri: self.jwks_uri.clone(),
+            expires_at: Utc::now() + Duration::seconds(ttl),
+            keys,
+        };
+        if !token_store::persistence_disabled() {
+            if let Err(e) = write_atomic(&self.path, serde_json::to_string(&cached)?.as_bytes()) {
+                eprintln!("[oauth-info] Could not cache JWKS: {}", e);
+            }
+        }
+        self.cached = Some(cached);
+        Ok(())
+    }
+}
+
+// `max-age` from a Cache-Control header; `no-cache`/`no-store` mean do not reuse.
+fn max_age(cache_control: &str) -> Option<i64> {
+    cache_control
+        .split(',')
+        .map(str::trim)
+        .find_map(|directive| {
+            if directive.eq_ignore_ascii_case("no-cache")
+                || directive.eq_ignore_ascii_case("no-store")
+            {
+                return Some(0);
+            }
+            directive
+                .strip_prefix("max-age=")
+                .and_then(|secs| secs.trim_matches('"').parse().ok())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn jwks(kid: &str) -> serde_json::Value {
+        json!({"keys": [{"kty": "oct", "kid": kid, "k": "c2VjcmV0LWtleQ"}]})
+    }
+
+    #[test]
+    fn test_max_age() {
+        assert_eq!(max_age("public, max-age=600, must-revalidate"), Some(600));
+        assert_eq!(max_age("no-store"), Some(0));
+        assert_eq!(max_age("public"), None);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_kid_triggers_refetch() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("cache-control", "max-age=3600")
+                    .set_body_json(jwks("old-key")),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(jwks("rotated-key")))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let uri = format!("{}/jwks", server.uri());
+        let mut cache = JwksCache::open_in(dir.path(), &uri);
+        cache.refresh().await.unwrap();
+        assert!(cache.is_fresh());
+
+        cache.decoding_key("rotated-key").await.unwrap();
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        // The refetched set was written to disk for the next run
+        let reopened = JwksCache::open_in(dir.path(), &uri);
+        assert!(reopened.find("rotated-key").unwrap().is_some());
+    }
+}
@@ -0,0 +1,158 @@
+//! One browser login at a time. A second `goose auth login` started while the
+//! first is still waiting for its callback would only fail to bind the same
+//! port, so it stops early with "another login is in progress" instead.
+//!
+//! The lock is a file in the auth config dir holding the owner's pid. It is
+//! removed when the login ends however it ends; one left behind by a crashed
+//! process is taken over once that process is gone. With `--no-persist`
+//! nothing may touch the disk, so the lock only covers this process.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::token_store;
+
+const LOGIN_LOCK_FILE: &str = "login.lock";
+
+/// The lock taken instead of the file when persistence is disabled
+static IN_PROCESS: AtomicBool = AtomicBool::new(false);
+
+/// Held for the duration of a login; dropping it releases the lock.
+#[derive(Debug)]
+pub struct LoginLock {
+    /// `None` for the in-process lock
+    path: Option<PathBuf>,
+}
+
+impl LoginLock {
+    pub fn acquire() -> Result<Self> {
+        if token_store::persistence_disabled() {
+            return Self::acquire_in_process();
+        }
+        Self::acquire_at(&token_store::auth_dir()?.join(LOGIN_LOCK_FILE))
+    }
+
+    fn acquire_in_process() -> Result<Self> {
+        if IN_PROCESS.swap(true, Ordering::SeqCst) {
+            return Err(anyhow!("Another login is in progress in this process"));
+        }
+        Ok(Self { path: None })
+    }
+
+    fn acquire_at(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // A second attempt only after removing a stale lock
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    write!(file, "{}", std::process::id())?;
+                    return Ok(Self {
+                        path: Some(path.to_path_buf()),
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e).context("Failed to create the login lock"),
+            }
+            let owner = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|pid| pid.trim().parse::<u32>().ok());
+            // No pid yet means the owner has only just created the file
+            match owner {
+                Some(pid) if !process_alive(pid) => {
+                    tracing::debug!(pid, path = %path.display(), "removing stale login lock");
+                    let _ = std::fs::remove_file(path);
+                }
+                _ => break,
+            }
+        }
+        Err(anyhow!(
+            "Another login is in progress; wait for it to finish, or remove {} if it is stuck",
+            path.display()
+        ))
+    }
+}
+
+impl Drop for LoginLock {
+    fn drop(&mut self) {
+        match &self.path {
+            Some(path) => {
+                let _ = std::fs::remove_file(path);
+            }
+            None => IN_PROCESS.store(false, Ordering::SeqCst),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+    // Signal 0 only checks; EPERM means it exists but belongs to someone else
+    matches!(
+        kill(Pid::from_raw(pid as i32), None),
+        Ok(()) | Err(Errno::EPERM)
+    )
+}
+
+// Without a cheap liveness check, a leftover lock has to be removed by hand.
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_login_is_refused_until_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOGIN_LOCK_FILE);
+        let lock = LoginLock::acquire_at(&path).unwrap();
+        let err = LoginLock::acquire_at(&path).unwrap_err();
+        assert!(err.to_string().contains("Another login is in progress"));
+
+        drop(lock);
+        assert!(!path.exists());
+        LoginLock::acquire_at(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_lock_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOGIN_LOCK_FILE);
+        // Far above any real pid_max
+        std::fs::write(&path, "999999999").unwrap();
+        let _lock = LoginLock::acquire_at(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            std::process::id().to_string()
+        );
+    }
+
+    #[test]
+    fn test_no_persist_lock_stays_in_process() {
+        let home = tempfile::tempdir().unwrap();
+        temp_env::with_vars(
+            [
+                ("HOME", Some(home.path().to_str().unwrap())),
+                ("XDG_CONFIG_HOME", None),
+                ("GOOSE_AUTH_NO_PERSIST", Some("1")),
+            ],
+            || {
+                let lock = LoginLock::acquire().unwrap();
+                assert!(LoginLock::acquire().is_err());
+                assert_eq!(std::fs::read_dir(home.path()).unwrap().count(), 0);
+                drop(lock);
+                LoginLock::acquire().unwrap();
+            },
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Wording of the interactive login prompts, so it can be translated or
+//! adapted by an embedder without touching the flow.
+//!
+//! `GOOSE_MESSAGES` names a JSON file, or TOML when it ends in `.toml`, that
+//! maps message ids to replacement text:
+//!
+//! ```toml
+//! please_log_in = "Bitte melden Sie sich an"
+//! authenticated_as = "Angemeldet als {login}"
+//! ```
+//!
+//! `{name}` placeholders are filled in as in the built-in text. Any id the
+//! file leaves out keeps the English below; a file that cannot be read is
+//! reported once and ignored.
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Built-in English text, by message id
+const ENGLISH: &[(&str, &str)] = &[
+    ("authenticated_as", "Authenticated as {login}"),
+    ("please_log_in", "Please log in"),
+    (
+        "select_mode_inline",
+        "Select authentication mode: [a]utomatic (callback) / [m]anual (paste URL) / [d]evice (enter a code) [a]: ",
+    ),
+    ("select_mode", "Select authentication mode:"),
+    ("mode_automatic", "Automatic (callback server)"),
+    ("mode_manual", "Manual (paste redirected URL)"),
+    ("enter_choice", "Enter choice [1]: "),
+    ("manual_heading", "Manual OAuth fallback"),
+    ("manual_open_url", "Open the printed URL in your browser"),
+    ("manual_copy", "After authorizing, copy either:"),
+    ("manual_copy_url", "the full redirected URL you land on, OR"),
+    ("manual_copy_code", "just the value of the 'code' parameter"),
+    ("paste_here", "Paste here and press Enter: "),
+    ("missing_state", "The pasted value has no 'state' parameter."),
+    (
+        "paste_state",
+        "Paste the state from the redirected URL, or type 'skip' to continue without checking it: ",
+    ),
+    ("paste_json", "Paste the JSON response, then press Enter: "),
+];
+
+static OVERRIDES: Lazy<HashMap<String, String>> = Lazy::new(|| {
+    let Some(path) = std::env::var("GOOSE_MESSAGES")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+    else {
+        return HashMap::new();
+    };
+    load(Path::new(path.trim())).unwrap_or_else(|e| {
+        eprintln!(
+            "[oauth-info] Ignoring GOOSE_MESSAGES: {:#}; using the built-in messages",
+            e
+        );
+        HashMap::new()
+    })
+});
+
+/// The text for `id`, with no placeholders to fill.
+pub fn text(id: &str) -> String {
+    format(id, &[])
+}
+
+/// The text for `id` with each `{name}` replaced by its value in `args`.
+pub fn format(id: &str, args: &[(&str, &str)]) -> String {
+    lookup(&OVERRIDES, id, args)
+}
+
+fn lookup(overrides: &HashMap<String, String>, id: &str, args: &[(&str, &str)]) -> String {
+    let template = overrides
+        .get(id)
+        .map(String::as_str)
+        .or_else(|| english(id))
+        .unwrap_or(id);
+    args.iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+fn english(id: &str) -> Option<&'static str> {
+    ENGLISH
+        .iter()
+        .find(|(key, _)| *key == id)
+        .map(|(_, text)| *text)
+}
+
+fn load(path: &Path) -> Result<HashMap<String, String>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("{} cannot be read: {}", path.display(), e))?;
+    let messages: HashMap<String, String> = if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+    {
+        toml::from_str(&contents)
+            .map_err(|e| anyhow!("{} is not valid TOML: {}", path.display(), e))?
+    } else {
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("{} is not valid JSON: {}", path.display(), e))?
+    };
+    for id in messages.keys().filter(|id| english(id).is_none()) {
+        tracing::debug!(id = %id, "GOOSE_MESSAGES sets an unknown message id");
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_fall_back_to_english() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("messages.toml");
+        std::fs::write(&path, "authenticated_as = \"Angemeldet als {login}\"\n").unwrap();
+        let overrides = load(&path).unwrap();
+        assert_eq!(
+            lookup(&overrides, "authenticated_as", &[("login", "octocat")]),
+            "Angemeldet als octocat"
+        );
+        assert_eq!(lookup(&overrides, "please_log_in", &[]), "Please log in");
+
+        let json = dir.path().join("messages.json");
+        std::fs::write(&json, r#"{"please_log_in": "Connectez-vous"}"#).unwrap();
+        assert_eq!(
+            lookup(&load(&json).unwrap(), "please_log_in", &[]),
+            "Connectez-vous"
+        );
+        std::fs::write(&json, "please_log_in = 1").unwrap();
+        assert!(load(&json).is_err());
+    }
+}
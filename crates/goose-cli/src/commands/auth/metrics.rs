@@ -0,0 +1,124 @@
+//! Prometheus textfile metrics for fleet-wide auth health.
+//!
+//! When `GOOSE_AUTH_METRICS_FILE` is set, every `goose auth` command rewrites
+//! that file atomically so a node-exporter textfile collector can scrape it:
+//!
+//! - `goose_auth_last_success_timestamp` (gauge): Unix time of the last auth
+//!   command that succeeded; carried over from the previous file on failure.
+//! - `goose_auth_token_expiry_timestamp` (gauge): Unix time at which the stored
+//!   token expires. Omitted when no token is stored or its expiry is unknown.
+//! - `goose_auth_last_result{result="success"|"failure"}` (gauge): 1 for the
+//!   outcome of the most recent auth command, 0 for the other.
+//!
+//! The file never contains tokens or other secrets.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use super::token_store::write_atomic;
+
+const LAST_SUCCESS: &str = "goose_auth_last_success_timestamp";
+const TOKEN_EXPIRY: &str = "goose_auth_token_expiry_timestamp";
+const LAST_RESULT: &str = "goose_auth_last_result";
+
+pub fn metrics_path() -> Option<PathBuf> {
+    std::env::var("GOOSE_AUTH_METRICS_FILE")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// Render the textfile. `previous` is the old file's contents, used to keep the
+/// last success timestamp when this run failed.
+pub fn render(
+    success: bool,
+    now: DateTime<Utc>,
+    token_expiry: Option<DateTime<Utc>>,
+    previous: Option<&str>,
+) -> String {
+    let last_success = if success {
+        Some(now.timestamp())
+    } else {
+        previous.and_then(|contents| read_gauge(contents, LAST_SUCCESS))
+    };
+
+    let mut out = String::new();
+    if let Some(ts) = last_success {
+        let _ = writeln!(
+            out,
+            "# HELP {LAST_SUCCESS} Unix time of the last successful goose auth command.\n\
+             # TYPE {LAST_SUCCESS} gauge\n\
+             {LAST_SUCCESS} {ts}"
+        );
+    }
+    if let Some(expiry) = token_expiry {
+        let _ = writeln!(
+            out,
+            "# HELP {TOKEN_EXPIRY} Unix time at which the stored goose auth token expires.\n\
+             # TYPE {TOKEN_EXPIRY} gauge\n\
+             {TOKEN_EXPIRY} {}",
+            expiry.timestamp()
+        );
+    }
+    let _ = writeln!(
+        out,
+        "# HELP {LAST_RESULT} Outcome of the most recent goose auth command.\n\
+         # TYPE {LAST_RESULT} gauge\n\
+         {LAST_RESULT}{{result=\"success\"}} {}\n\
+         {LAST_RESULT}{{result=\"failure\"}} {}",
+        u8::from(success),
+        u8::from(!success)
+    );
+    out
+}
+
+fn read_gauge(contents: &str, name: &str) -> Option<i64> {
+    contents.lines().find_map(|line| {
+        let value = line.strip_prefix(name)?.strip_prefix(' ')?;
+        value.trim().parse().ok()
+    })
+}
+
+pub fn write(path: &Path, success: bool, token_expiry: Option<DateTime<Utc>>) -> Result<()> {
+    let previous = std::fs::read_to_string(path).ok();
+    let contents = render(success, Utc::now(), token_expiry, previous.as_deref());
+    write_atomic(path, contents.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_render_success() {
+        let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let expiry = Utc.timestamp_opt(1_700_028_800, 0).unwrap();
+        let out = render(true, now, Some(expiry), None);
+        assert!(out.contains("goose_auth_last_success_timestamp 1700000000\n"));
+        assert!(out.contains("goose_auth_token_expiry_timestamp 1700028800\n"));
+        assert!(out.contains("goose_auth_last_result{result=\"success\"} 1\n"));
+        assert!(out.contains("goose_auth_last_result{result=\"failure\"} 0\n"));
+    }
+
+    #[test]
+    fn test_failure_keeps_previous_success_timestamp() {
+        let earlier = render(
+            true,
+            Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+            None,
+            None,
+        );
+        let out = render(
+            false,
+            Utc.timestamp_opt(1_700_000_500, 0).unwrap(),
+            None,
+            Some(&earlier),
+        );
+        assert!(out.contains("goose_auth_last_success_timestamp 1700000000\n"));
+        assert!(out.contains("goose_auth_last_result{result=\"failure\"} 1\n"));
+        assert!(!out.contains("goose_auth_token_expiry_timestamp"));
+    }
+}
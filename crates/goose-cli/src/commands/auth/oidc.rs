@@ -0,0 +1,146 @@
+//! OIDC discovery: the endpoints of any OpenID Connect issuer (Okta, Auth0,
+//! Keycloak, Azure AD, ...) learned from its `/.well-known/openid-configuration`.
+//!
+//! Documents are cached for the lifetime of the process, so a multi-provider
+//! login or a status check after a login fetches each issuer once.
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::{http, AuthError};
+
+const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+static DISCOVERY_CACHE: Lazy<Mutex<HashMap<String, Discovery>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The parts of a discovery document goose uses.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Discovery {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub userinfo_endpoint: Option<String>,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+    /// Keys that sign the issuer's id_tokens
+    #[serde(default)]
+    pub jwks_uri: Option<String>,
+    /// For the device flow, when the issuer offers it
+    #[serde(default)]
+    pub device_authorization_endpoint: Option<String>,
+}
+
+/// `GOOSE_OIDC_ISSUER`
+pub fn issuer_from_env() -> Result<String> {
+    std::env::var("GOOSE_OIDC_ISSUER")
+        .ok()
+        .map(|issuer| issuer.trim().to_string())
+        .filter(|issuer| !issuer.is_empty())
+        .ok_or_else(|| {
+            AuthError::ConfigMissing(
+                "GOOSE_OIDC_ISSUER must be set to use the oidc provider".into(),
+            )
+            .into()
+        })
+}
+
+/// Discovery document for `issuer`, fetched on first use.
+pub async fn discover(issuer: &str) -> Result<Discovery> {
+    let issuer = issuer.trim().trim_end_matches('/');
+    if let Some(discovery) = DISCOVERY_CACHE.lock().unwrap().get(issuer) {
+        return Ok(discovery.clone());
+    }
+    let discovery = fetch(issuer).await?;
+    DISCOVERY_CACHE
+        .lock()
+        .unwrap()
+        .insert(issuer.to_string(), discovery.clone());
+    Ok(discovery)
+}
+
+async fn fetch(issuer: &str) -> Result<Discovery> {
+    let url = format!("{}{}", issuer, DISCOVERY_PATH);
+    let response = http::build_http_client()?
+        .get(&url, None)
+        .await
+        .map_err(|e| anyhow!("Could not reach OIDC issuer {}: {}", issuer, e))?;
+    if !response.is_success() {
+        return Err(anyhow!(
+            "OIDC discovery at {} returned HTTP {}",
+            url,
+            response.status
+        ));
+    }
+    let json = response
+        .json()
+        .map_err(|e| anyhow!("Malformed OIDC discovery document at {}: {}", url, e))?;
+    parse_discovery(issuer, json)
+        .map_err(|e| anyhow!("Malformed OIDC discovery document at {}: {}", url, e))
+}
+
+fn parse_discovery(issuer: &str, json: Value) -> Result<Discovery> {
+    let discovery: Discovery = serde_json::from_value(json)?;
+    // OIDC Discovery section 4.3: the document must name the issuer it was fetched from
+    if discovery.issuer.trim_end_matches('/') != issuer {
+        return Err(anyhow!(
+            "issuer is '{}', expected '{}'",
+            discovery.issuer,
+            issuer
+        ));
+    }
+    Ok(discovery)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_discovery_is_fetched_once() {
+        let server = MockServer::start().await;
+        let issuer = server.uri();
+        Mock::given(method("GET"))
+            .and(path(DISCOVERY_PATH))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{}/authorize", issuer),
+                "token_endpoint": format!("{}/token", issuer),
+                "scopes_supported": ["openid", "email"]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let discovery = discover(&format!("{}/", issuer)).await.unwrap();
+        assert_eq!(discovery.token_endpoint, format!("{}/token", issuer));
+        assert_eq!(discovery.scopes_supported, ["openid", "email"]);
+        assert_eq!(discover(&issuer).await.unwrap(), discovery);
+    }
+
+    #[test]
+    fn test_malformed_discovery_is_rejected() {
+        let issuer = "https://login.example.com";
+        assert!(parse_discovery(issuer, json!({"issuer": issuer})).is_err());
+        let err = parse_discovery(
+            issuer,
+            json!({
+                "issuer": "https://evil.example.com",
+                "authorization_endpoint": "https://evil.example.com/authorize",
+                "token_endpoint": "https://evil.example.com/token"
+            }),
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("expected 'https://login.example.com'"));
+    }
+}
@@ -0,0 +1,98 @@
+//! `--output json` / `GOOSE_OUTPUT=json` for `goose auth`.
+//!
+//! In JSON mode each command writes exactly one JSON object to stdout, and
+//! every human-facing message moves to stderr. Failures that did not already
+//! produce a result are reported as `{"error": "..."}`.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+/// Set once a result object was written, so errors are not reported twice
+static EMITTED: AtomicBool = AtomicBool::new(false);
+
+/// `println!` for human-facing messages: stdout normally, stderr in JSON mode
+/// so stdout carries only the result object.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::commands::auth::output::is_json() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+pub(crate) use say;
+
+/// Select the format from `--output`, else `GOOSE_OUTPUT`, else text.
+pub fn configure(flag: Option<&str>) -> Result<()> {
+    let format = flag
+        .map(str::to_string)
+        .or_else(|| std::env::var("GOOSE_OUTPUT").ok())
+        .map(|format| format.trim().to_lowercase())
+        .filter(|format| !format.is_empty());
+    let json = match format.as_deref() {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(other) => {
+            return Err(anyhow!(
+                "Unknown output format '{}', expected text or json",
+                other
+            ))
+        }
+    };
+    JSON.store(json, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::SeqCst)
+}
+
+/// Print an inline prompt where [`say!`] would send a message, and flush it.
+pub fn prompt(text: &str) {
+    if is_json() {
+        eprint!("{}", text);
+        let _ = std::io::stderr().flush();
+    } else {
+        print!("{}", text);
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Write the command's result object; a no-op in text mode.
+pub fn emit(value: Value) {
+    if is_json() {
+        EMITTED.store(true, Ordering::SeqCst);
+        println!("{}", value);
+    }
+}
+
+/// Report a failed command as `{"error": "..."}` unless it already wrote a
+/// result, such as `status` reporting `"authenticated": false`.
+pub fn emit_error(err: &anyhow::Error) {
+    if is_json() && !EMITTED.load(Ordering::SeqCst) {
+        emit(error_object(err));
+    }
+}
+
+fn error_object(err: &anyhow::Error) -> Value {
+    json!({ "error": super::redact::redact(&err.to_string()) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_object_shape() {
+        let err = anyhow!("Token exchange failed: timeout");
+        assert_eq!(
+            error_object(&err),
+            json!({"error": "Token exchange failed: timeout"})
+        );
+        assert!(configure(Some("yaml")).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! HTML served by the callback server once the browser comes back.
+//!
+//! `GOOSE_AUTH_SUCCESS_HTML` and `GOOSE_AUTH_ERROR_HTML` name files that
+//! replace the built-in success and invalid-state pages. Both may contain a
+//! `{{redirect}}` placeholder, filled with `GOOSE_AUTH_SUCCESS_REDIRECT`
+//! (HTML-escaped, empty when unset) so a page can close the tab or bounce to
+//! the integrator's own app.
+//!
+//! `GOOSE_AUTH_AUTOCLOSE=1` adds a `window.close()` script to the success page.
+//! Browsers only honor it for tabs a script opened, so the page's own message
+//! stays for the rest.
+
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+const DEFAULT_SUCCESS_HTML: &str =
+    "<html><body><h3>Authentication succeeded. You can close this window.</h3></body></html>";
+const DEFAULT_ERROR_HTML: &str = "<html><body><h3>Invalid state parameter.</h3></body></html>";
+const REDIRECT_PLACEHOLDER: &str = "{{redirect}}";
+const AUTOCLOSE_SCRIPT: &str = "<script>window.close()</script>";
+
+#[derive(Debug, Clone)]
+pub struct CallbackPages {
+    pub success: String,
+    pub error: String,
+}
+
+impl CallbackPages {
+    /// Load and render both pages. Runs before the browser is opened, so a
+    /// missing or empty file fails the login up front.
+    pub fn from_env() -> Result<Self> {
+        let redirect = std::env::var("GOOSE_AUTH_SUCCESS_REDIRECT").unwrap_or_default();
+        let mut success = render(
+            &load("GOOSE_AUTH_SUCCESS_HTML", DEFAULT_SUCCESS_HTML)?,
+            &redirect,
+        );
+        if std::env::var("GOOSE_AUTH_AUTOCLOSE").as_deref() == Ok("1") {
+            success = with_autoclose(&success);
+        }
+        Ok(Self {
+            success,
+            error: render(
+                &load("GOOSE_AUTH_ERROR_HTML", DEFAULT_ERROR_HTML)?,
+                &redirect,
+            ),
+        })
+    }
+}
+
+fn load(var: &str, default: &str) -> Result<String> {
+    let Some(path) = std::env::var(var)
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+    else {
+        return Ok(default.to_string());
+    };
+    let path = Path::new(path.trim());
+    let html = std::fs::read_to_string(path).map_err(|e| {
+        anyhow!(
+            "{} points at {}, which cannot be read: {}",
+            var,
+            path.display(),
+            e
+        )
+    })?;
+    if html.trim().is_empty() {
+        return Err(anyhow!(
+            "{} points at {}, which is empty",
+            var,
+            path.display()
+        ));
+    }
+    Ok(html)
+}
+
+fn render(template: &str, redirect: &str) -> String {
+    template.replace(REDIRECT_PLACEHOLDER, &escape_html(redirect))
+}
+
+// Just before `</body>` when there is one, so the message renders first.
+fn with_autoclose(html: &str) -> String {
+    match html.to_ascii_lowercase().rfind("</body>") {
+        Some(end) => format!("{}{}{}", &html[..end], AUTOCLOSE_SCRIPT, &html[end..]),
+        None => format!("{}{}", html, AUTOCLOSE_SCRIPT),
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_placeholder_is_escaped() {
+        let html = render(
+            r#"<meta http-equiv="refresh" content="0; url={{redirect}}">"#,
+            r#"https://app.example.com/?a=1&b="x""#,
+        );
+        assert_eq!(
+            html,
+            r#"<meta http-equiv="refresh" content="0; url=https://app.example.com/?a=1&amp;b=&quot;x&quot;">"#
+        );
+    }
+
+    #[test]
+    fn test_autoclose_keeps_the_message() {
+        let html = with_autoclose(DEFAULT_SUCCESS_HTML);
+        assert!(html.contains("You can close this window."));
+        assert!(html.ends_with("<script>window.close()</script></body></html>"));
+        assert_eq!(
+            with_autoclose("<h3>Done</h3>"),
+            "<h3>Done</h3><script>window.close()</script>"
+        );
+    }
+
+    #[test]
+    fn test_missing_page_file_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("success.html");
+        temp_env::with_var("GOOSE_AUTH_SUCCESS_HTML", Some(&missing), || {
+            let err = CallbackPages::from_env().unwrap_err();
+            assert!(err.to_string().contains("cannot be read"));
+        });
+    }
+}
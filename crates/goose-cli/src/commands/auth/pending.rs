@@ -0,0 +1,190 @@
+//! A login split across two commands: `goose auth url` prints the authorize
+//! URL and stashes what the code exchange needs here, and `goose auth
+//! exchange` finishes it. An automatic login run with `GOOSE_AUTH_RESUMABLE=1`
+//! stashes the same, so it can be finished that way if goose is killed while
+//! waiting for the callback. The stash is sealed with [`secret_state`] and only
+//! good for [`PENDING_LOGIN_TTL_SECS`], so a forgotten verifier does not linger.
+//!
+//! [`secret_state`]: super::secret_state
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::redact::SensitiveString;
+use super::secret_state::{self, StateKey};
+use super::token_store;
+
+/// How long a printed authorize URL can still be exchanged
+pub const PENDING_LOGIN_TTL_SECS: i64 = 10 * 60;
+const PENDING_LOGIN_FILE: &str = "pending_login.state";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingLogin {
+    pub provider: String,
+    pub account: String,
+    pub client_id: String,
+    /// Exactly as sent in the authorize URL; the exchange must repeat it
+    pub redirect_url: String,
+    pub scopes: Vec<String>,
+    pub state: String,
+    pub code_verifier: SensitiveString,
+    /// Sent for OIDC logins, checked against the id_token
+    #[serde(default)]
+    pub nonce: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PendingLogin {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.created_at + chrono::Duration::seconds(PENDING_LOGIN_TTL_SECS)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at()
+    }
+
+    /// Replace any earlier pending login with this one.
+    pub fn save(&self) -> Result<()> {
+        save_to(&StateKey::from_env()?, &pending_path()?, self)
+    }
+}
+
+/// The pending login to finish, or an error saying why there is none. An
+/// expired one is removed on the way.
+pub fn load() -> Result<PendingLogin> {
+    load_from(&StateKey::from_env()?, &pending_path()?, Utc::now())
+}
+
+/// Forget the pending login, e.g. once its code has been redeemed.
+pub fn clear() -> Result<()> {
+    clear_at(&pending_path()?)
+}
+
+/// Removes the pending login an automatic login stashed when dropped, so a
+/// login that failed or finished another way leaves no verifier behind.
+pub struct ClearOnDrop {
+    path: Option<PathBuf>,
+}
+
+impl ClearOnDrop {
+    pub fn new() -> Result<Self> {
+        Ok(Self::at(pending_path()?))
+    }
+
+    fn at(path: PathBuf) -> Self {
+        Self { path: Some(path) }
+    }
+
+    /// Leave the pending login for `goose auth exchange`.
+    pub fn keep(mut self) {
+        self.path = None;
+    }
+
+    /// Remove it now, reporting a failure that dropping would only log.
+    pub fn clear(mut self) -> Result<()> {
+        match self.path.take() {
+            Some(path) => clear_at(&path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ClearOnDrop {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            if let Err(e) = clear_at(&path) {
+                tracing::warn!(error = %e, "could not remove the pending login");
+            }
+        }
+    }
+}
+
+fn pending_path() -> Result<PathBuf> {
+    Ok(token_store::auth_dir()?.join(PENDING_LOGIN_FILE))
+}
+
+fn save_to(key: &StateKey, path: &Path, login: &PendingLogin) -> Result<()> {
+    secret_state::write_state(key, path, login)
+}
+
+fn load_from(key: &StateKey, path: &Path, now: DateTime<Utc>) -> Result<PendingLogin> {
+    let login: PendingLogin = secret_state::read_state(key, path)?
+        .ok_or_else(|| anyhow!("No login is pending; run `goose auth url` first"))?;
+    if login.is_expired(now) {
+        clear_at(path)?;
+        return Err(anyhow!(
+            "The pending login expired at {}; start it again",
+            login.expires_at().to_rfc3339()
+        ));
+    }
+    Ok(login)
+}
+
+fn clear_at(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).context("Failed to remove the pending login"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending_login(created_at: DateTime<Utc>) -> PendingLogin {
+        PendingLogin {
+            provider: "github".into(),
+            account: "default".into(),
+            client_id: "client".into(),
+            redirect_url: "http://127.0.0.1:8080/oauth_callback".into(),
+            scopes: vec!["read:user".into()],
+            state: "state".into(),
+            code_verifier: "verifier".into(),
+            nonce: None,
+            created_at,
+        }
+    }
+
+    #[test]
+    fn test_pending_login_expires() {
+        let created_at = Utc::now();
+        let login = pending_login(created_at);
+        assert!(!login.is_expired(created_at + chrono::Duration::minutes(9)));
+        assert!(login.is_expired(created_at + chrono::Duration::minutes(10)));
+    }
+
+    #[test]
+    fn test_load_pending_login() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = StateKey::Machine(dir.path().join("state.key"));
+        let path = dir.path().join(PENDING_LOGIN_FILE);
+        let now = Utc::now();
+
+        let err = load_from(&key, &path, now).unwrap_err();
+        assert!(err.to_string().contains("No login is pending"));
+
+        let login = pending_login(now);
+        save_to(&key, &path, &login).unwrap();
+        assert_eq!(load_from(&key, &path, now).unwrap(), login);
+
+        let err = load_from(&key, &path, now + chrono::Duration::hours(1)).unwrap_err();
+        assert!(err.to_string().contains("expired"));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_clear_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = StateKey::Machine(dir.path().join("state.key"));
+        let path = dir.path().join(PENDING_LOGIN_FILE);
+        save_to(&key, &path, &pending_login(Utc::now())).unwrap();
+
+        ClearOnDrop::at(path.clone()).keep();
+        assert!(path.exists());
+        drop(ClearOnDrop::at(path.clone()));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,614 @@
+//! OAuth providers goose can log into.
+//!
+//! `--provider` or `GOOSE_AUTH_PROVIDER` picks a preset (`github`, the default,
+//! or `gitlab`), or `oidc` for any OpenID Connect issuer named by
+//! `GOOSE_OIDC_ISSUER`, whose endpoints come from its discovery document. Any
+//! preset's endpoints can be overridden with `GOOSE_AUTH_AUTHORIZE_URL`,
+//! `GOOSE_AUTH_TOKEN_URL`, `GOOSE_AUTH_IDENTITY_URL` and
+//! `GOOSE_AUTH_DEFAULT_SCOPES`, e.g. for a self-managed GitLab. A provider
+//! without a preset uses `custom`, which requires the authorize, token and
+//! identity URLs to be set and otherwise behaves like any other provider.
+//!
+//! The device flow uses the provider's device authorization endpoint: built in
+//! for GitHub and GitLab, from discovery for OIDC, and set or replaced with
+//! `GOOSE_AUTH_DEVICE_URL`. Overriding a preset's token URL without it leaves
+//! the provider with no device flow rather than the preset's endpoint.
+//!
+//! A provider no preset covers can also be defined in a JSON file named by
+//! `--provider-config` or `GOOSE_AUTH_PROVIDER_CONFIG`; see [`provider_config`].
+//!
+//! `GOOSE_GITHUB_HOST` points `github` and everything else that talks to
+//! GitHub (device flow, revocation, GitHub Apps) at an Enterprise Server, e.g.
+//! `github.mycorp.com`, whose REST API lives under `/api/v3`.
+//!
+//! Without a named provider, one is inferred where possible: `github` when
+//! `GOOSE_GITHUB_HOST` is set, else the preset whose SaaS host the redirect
+//! URL is on (`gitlab.com` or a subdomain of it). The choice is reported once.
+//! A self-hosted-looking host such as `gitlab.mycorp.com` is never mapped to a
+//! preset, whose endpoints would be wrong there; instead a hint names the
+//! `GOOSE_GITHUB_HOST` or `GOOSE_AUTH_*_URL` settings it needs.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::sync::Once;
+use url::Url;
+
+use super::id_token::IdTokenIssuer;
+use super::oidc::{self, Discovery};
+use super::provider_config;
+use super::scopes::ScopeDelimiter;
+
+/// Names accepted by `--provider`
+const PRESETS: &[&str] = &["github", "gitlab", "oidc", "custom"];
+
+/// Reports the inferred provider on first use only
+static DETECTED: Once = Once::new();
+/// Points a self-hosted-looking redirect host at its settings, once
+static SELF_HOSTED_HINT: Once = Once::new();
+
+/// Requested from OIDC issuers when the discovery document lists them
+const OIDC_SCOPES: &[&str] = &["openid", "profile", "email"];
+
+/// OAuth endpoints of an identity provider. Every provider stores its token
+/// under its own namespace, so logging into one never replaces another's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provider {
+    pub name: String,
+    pub authorize_url: String,
+    pub token_url: String,
+    /// Bearer-authenticated endpoint that returns the signed-in user
+    pub user_url: String,
+    /// Field of the `user_url` response naming the user
+    pub login_field: String,
+    /// Scopes requested when none are configured
+    pub default_scopes: String,
+    /// Joins scopes in the authorize request
+    pub scope_delimiter: ScopeDelimiter,
+    /// Set for OIDC issuers, whose logins send a nonce and verify the id_token
+    pub id_token: Option<IdTokenIssuer>,
+    /// Send a PKCE challenge with the authorize request
+    pub uses_pkce: bool,
+    /// How the client secret reaches the token endpoint
+    pub client_auth_method: ClientAuthMethod,
+    /// Device authorization endpoint (RFC 8628), for providers offering the
+    /// device flow
+    pub device_authorization_url: Option<String>,
+}
+
+/// How a confidential client authenticates to the token endpoint, named as
+/// in OIDC Core section 9.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAuthMethod {
+    /// `client_secret` in the form body
+    #[default]
+    ClientSecretPost,
+    /// HTTP Basic with the client id and secret
+    ClientSecretBasic,
+    /// Never send the secret
+    None,
+}
+
+impl Default for Provider {
+    fn default() -> Self {
+        Self::github()
+    }
+}
+
+impl Provider {
+    pub fn github() -> Self {
+        Self {
+            name: "github".to_string(),
+            authorize_url: "https://github.com/login/oauth/authorize".to_string(),
+            token_url: "https://github.com/login/oauth/access_token".to_string(),
+            user_url: "https://api.github.com/user".to_string(),
+            login_field: "login".to_string(),
+            default_scopes: "read:user user:email".to_string(),
+            scope_delimiter: ScopeDelimiter::Space,
+            id_token: None,
+            uses_pkce: true,
+            client_auth_method: ClientAuthMethod::ClientSecretPost,
+            device_authorization_url: Some("https://github.com/login/device/code".to_string()),
+        }
+    }
+
+    /// GitHub Enterprise Server at `host`, as returned by [`github_host`]
+    pub fn github_enterprise(host: &str) -> Self {
+        Self {
+            authorize_url: format!("https://{}/login/oauth/authorize", host),
+            token_url: format!("https://{}/login/oauth/access_token", host),
+            user_url: format!("https://{}/api/v3/user", host),
+            device_authorization_url: Some(format!("https://{}/login/device/code", host)),
+            ..Self::github()
+        }
+    }
+
+    pub fn gitlab() -> Self {
+        Self {
+            name: "gitlab".to_string(),
+            authorize_url: "https://gitlab.com/oauth/authorize".to_string(),
+            token_url: "https://gitlab.com/oauth/token".to_string(),
+            user_url: "https://gitlab.com/api/v4/user".to_string(),
+            login_field: "username".to_string(),
+            default_scopes: "read_user".to_string(),
+            scope_delimiter: ScopeDelimiter::Space,
+            id_token: None,
+            uses_pkce: true,
+            client_auth_method: ClientAuthMethod::ClientSecretPost,
+            device_authorization_url: Some("https://gitlab.com/oauth/authorize_device".to_string()),
+        }
+    }
+
+    // Endpoints come entirely from the environment; see the module docs.
+    fn custom() -> Self {
+        Self {
+            name: "custom".to_string(),
+            authorize_url: String::new(),
+            token_url: String::new(),
+            user_url: String::new(),
+            login_field: "login".to_string(),
+            default_scopes: String::new(),
+            scope_delimiter: ScopeDelimiter::Space,
+            id_token: None,
+            uses_pkce: true,
+            client_auth_method: ClientAuthMethod::ClientSecretPost,
+            device_authorization_url: None,
+        }
+    }
+
+    /// Generic OIDC issuer described by `discovery`. The identity is the
+    /// userinfo `sub`, the only claim every issuer returns.
+    pub fn oidc(discovery: &Discovery) -> Self {
+        let default_scopes: Vec<&str> = OIDC_SCOPES
+            .iter()
+            .copied()
+            .filter(|scope| {
+                *scope == "openid"
+                    || discovery.scopes_supported.is_empty()
+                    || discovery.scopes_supported.iter().any(|s| s == scope)
+            })
+            .collect();
+        Self {
+            name: "oidc".to_string(),
+            authorize_url: discovery.authorization_endpoint.clone(),
+            token_url: discovery.token_endpoint.clone(),
+            user_url: discovery.userinfo_endpoint.clone().unwrap_or_default(),
+            login_field: "sub".to_string(),
+            default_scopes: default_scopes.join(" "),
+            scope_delimiter: ScopeDelimiter::Space,
+            id_token: Some(IdTokenIssuer {
+                issuer: discovery.issuer.clone(),
+                jwks_uri: discovery.jwks_uri.clone(),
+            }),
+            uses_pkce: true,
+            client_auth_method: ClientAuthMethod::ClientSecretPost,
+            device_authorization_url: discovery.device_authorization_endpoint.clone(),
+        }
+    }
+
+    /// Look up a preset by name, case-insensitively. `oidc` needs discovery
+    /// and is only available through [`Provider::resolve`].
+    fn by_name(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "github" => Ok(Self::github()),
+            "gitlab" => Ok(Self::gitlab()),
+            "custom" => Ok(Self::custom()),
+            other => Err(anyhow!(
+                "Unknown provider '{}', expected one of: {}",
+                other,
+                PRESETS.join(", ")
+            )),
+        }
+    }
+
+    /// The named provider, else `GOOSE_AUTH_PROVIDER` (or `provider` in
+    /// auth.toml), else the default, with the endpoint overrides from the
+    /// module docs and `GOOSE_AUTH_SCOPE_DELIMITER` applied. A provider config
+    /// file stands in for `custom` and the default, without overrides.
+    pub async fn resolve(name: Option<&str>) -> Result<Self> {
+        let name = match name {
+            Some(name) => Some(name.trim().to_lowercase()),
+            None => super::config::setting("GOOSE_AUTH_PROVIDER")?.map(|name| name.to_lowercase()),
+        };
+        if matches!(name.as_deref(), None | Some("custom")) {
+            if let Some(provider) = provider_config::load()? {
+                return Ok(provider);
+            }
+        }
+        let name = match name {
+            Some(name) => Some(name),
+            None => detect()?.map(|(name, reason)| {
+                DETECTED.call_once(|| {
+                    eprintln!(
+                        "[oauth-info] Using provider '{}' ({}); set GOOSE_AUTH_PROVIDER to choose another",
+                        name, reason
+                    );
+                });
+                name.to_string()
+            }),
+        };
+        let mut provider = match name.as_deref() {
+            None => Self::default(),
+            Some("oidc") => Self::oidc(&oidc::discover(&oidc::issuer_from_env()?).await?),
+            Some(name) => Self::by_name(name)?,
+        };
+        if provider.name == "github" {
+            if let Some(host) = github_host()? {
+                provider = Self::github_enterprise(&host);
+            }
+        }
+        provider.apply_env_overrides()?;
+        provider.scope_delimiter = ScopeDelimiter::from_env(provider.scope_delimiter)?;
+        Ok(provider)
+    }
+
+    /// The provider whose token is stored under `key`, for keys written by
+    /// [`Provider::token_key`].
+    pub async fn from_token_key(key: &str) -> Result<Self> {
+        let (name, _) = key
+            .split_once(':')
+            .ok_or_else(|| anyhow!("'{}' is not a provider token key", key))?;
+        Self::resolve(Some(name)).await
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<()> {
+        let fields = [
+            ("GOOSE_AUTH_AUTHORIZE_URL", &mut self.authorize_url),
+            ("GOOSE_AUTH_TOKEN_URL", &mut self.token_url),
+            ("GOOSE_AUTH_IDENTITY_URL", &mut self.user_url),
+            ("GOOSE_AUTH_IDENTITY_LOGIN_FIELD", &mut self.login_field),
+            ("GOOSE_AUTH_DEFAULT_SCOPES", &mut self.default_scopes),
+        ];
+        for (name, field) in fields {
+            if let Some(value) = env_override(name) {
+                *field = value;
+            }
+            if field.is_empty() && name != "GOOSE_AUTH_DEFAULT_SCOPES" {
+                return Err(anyhow!("Provider '{}' needs {} to be set", self.name, name));
+            }
+        }
+        // A preset's device endpoint belongs with its own token endpoint
+        if let Some(url) = env_override("GOOSE_AUTH_DEVICE_URL") {
+            self.device_authorization_url = Some(url);
+        } else if env_override("GOOSE_AUTH_TOKEN_URL").is_some() {
+            self.device_authorization_url = None;
+        }
+        Ok(())
+    }
+
+    /// How messages name the provider, e.g. "GitHub" in "GitHub OAuth error"
+    pub fn display_name(&self) -> &str {
+        match self.name.as_str() {
+            "github" => "GitHub",
+            "gitlab" => "GitLab",
+            "oidc" => "OIDC",
+            name => name,
+        }
+    }
+
+    /// Store key for this provider's token for `account`
+    pub fn token_key(&self, account: &str) -> String {
+        format!("{}:{}", self.name, account)
+    }
+}
+
+// The preset GOOSE_GITHUB_HOST or the redirect URL's host implies, and why.
+fn detect() -> Result<Option<(&'static str, String)>> {
+    let github_host = env_override("GOOSE_GITHUB_HOST");
+    if github_host.is_some() {
+        return Ok(detect_from(github_host.as_deref(), None));
+    }
+    let redirect_urls = super::config::setting("GOOSE_AUTH_REDIRECT_URL")?.unwrap_or_default();
+    let detected = redirect_urls
+        .split(',')
+        .find_map(|url| detect_from(None, Some(url.trim())));
+    if detected.is_none() {
+        if let Some((preset, host)) = redirect_urls
+            .split(',')
+            .find_map(|url| self_hosted_guess(url.trim()))
+        {
+            SELF_HOSTED_HINT.call_once(|| {
+                let hint = if preset == "github" {
+                    format!("set GOOSE_GITHUB_HOST={} to log in there", host)
+                } else {
+                    "set GOOSE_AUTH_PROVIDER=gitlab with GOOSE_AUTH_AUTHORIZE_URL, GOOSE_AUTH_TOKEN_URL and GOOSE_AUTH_IDENTITY_URL to log in there".to_string()
+                };
+                eprintln!(
+                    "[oauth-info] The redirect URL host {} looks like a self-hosted {}, which is not picked automatically; {}",
+                    host, preset, hint
+                );
+            });
+        }
+    }
+    Ok(detected)
+}
+
+fn detect_from(
+    github_host: Option<&str>,
+    redirect_url: Option<&str>,
+) -> Option<(&'static str, String)> {
+    if let Some(host) = github_host {
+        return Some(("github", format!("GOOSE_GITHUB_HOST is {}", host)));
+    }
+    let url = Url::parse(redirect_url?).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    ["github", "gitlab"]
+        .into_iter()
+        .find(|preset| {
+            // github.com and its subdomains only; a preset's endpoints are
+            // wrong for any self-hosted instance
+            let hosted = format!("{}.com", preset);
+            host == hosted || host.ends_with(&format!(".{}", hosted))
+        })
+        .map(|preset| (preset, format!("the redirect URL host is {}", host)))
+}
+
+// A host like github.mycorp.com: probably a self-hosted instance, whose
+// endpoints have to be configured rather than guessed.
+fn self_hosted_guess(redirect_url: &str) -> Option<(&'static str, String)> {
+    let host = Url::parse(redirect_url).ok()?.host_str()?.to_lowercase();
+    let first = host.split('.').next()?;
+    ["github", "gitlab"]
+        .into_iter()
+        .find(|preset| first == *preset && host.contains('.'))
+        .map(|preset| (preset, host))
+}
+
+/// `GOOSE_GITHUB_HOST`, or `None` for github.com.
+pub fn github_host() -> Result<Option<String>> {
+    parse_github_host(&std::env::var("GOOSE_GITHUB_HOST").unwrap_or_default())
+}
+
+// A bare host name, optionally with a port; anything that would change the
+// URLs built from it beyond the authority is refused.
+fn parse_github_host(raw: &str) -> Result<Option<String>> {
+    let raw = raw.trim().trim_end_matches('/');
+    if raw.is_empty() || raw.eq_ignore_ascii_case("github.com") {
+        return Ok(None);
+    }
+    let invalid = || {
+        anyhow!(
+            "Invalid GOOSE_GITHUB_HOST '{}': expected a host name such as github.mycorp.com",
+            raw
+        )
+    };
+    let url = Url::parse(&format!("https://{}/", raw)).map_err(|_| invalid())?;
+    let host = url.host_str().ok_or_else(invalid)?;
+    let authority = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+    if !authority.eq_ignore_ascii_case(raw) {
+        return Err(invalid());
+    }
+    Ok(Some(authority))
+}
+
+/// GitHub's web origin, where the OAuth and device flows live.
+pub fn github_web_url() -> Result<String> {
+    Ok(match github_host()? {
+        Some(host) => format!("https://{}", host),
+        None => "https://github.com".to_string(),
+    })
+}
+
+/// GitHub's REST API root.
+pub fn github_api_url() -> Result<String> {
+    Ok(match github_host()? {
+        Some(host) => format!("https://{}/api/v3", host),
+        None => "https://api.github.com".to_string(),
+    })
+}
+
+fn env_override(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve_provider() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("auth.toml");
+        std::fs::write(&config, "").unwrap();
+        temp_env::async_with_vars(
+            [
+                ("GOOSE_AUTH_PROVIDER", None),
+                ("GOOSE_GITHUB_HOST", None),
+                ("GOOSE_AUTH_REDIRECT_URL", None),
+                ("GOOSE_AUTH_PROVIDER_CONFIG", None),
+                ("GOOSE_AUTH_AUTHORIZE_URL", None),
+                ("GOOSE_AUTH_TOKEN_URL", None),
+                ("GOOSE_AUTH_IDENTITY_URL", None),
+                ("GOOSE_AUTH_DEVICE_URL", None),
+                ("GOOSE_AUTH_DEFAULT_SCOPES", None),
+                ("GOOSE_CONFIG", Some(config.to_str().unwrap())),
+            ],
+            async {
+                assert_eq!(Provider::resolve(None).await.unwrap(), Provider::github());
+                assert_eq!(
+                    Provider::resolve(Some("GitHub")).await.unwrap().name,
+                    "github"
+                );
+                let err = Provider::resolve(Some("bitbucket")).await.unwrap_err();
+                assert!(err.to_string().contains("expected one of: github, gitlab"));
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_device_endpoint_follows_token_endpoint() {
+        let cleared = [
+            ("GOOSE_AUTH_PROVIDER_CONFIG", None),
+            ("GOOSE_GITHUB_HOST", None),
+            ("GOOSE_AUTH_AUTHORIZE_URL", None),
+            ("GOOSE_AUTH_IDENTITY_URL", None),
+            ("GOOSE_AUTH_DEVICE_URL", None),
+        ];
+        let gitlab = temp_env::async_with_vars(
+            [
+                cleared.as_slice(),
+                &[(
+                    "GOOSE_AUTH_TOKEN_URL",
+                    Some("https://gitlab.mycorp.com/oauth/token"),
+                )],
+            ]
+            .concat(),
+            Provider::resolve(Some("gitlab")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(gitlab.device_authorization_url, None);
+
+        let gitlab = temp_env::async_with_vars(
+            [
+                cleared.as_slice(),
+                &[
+                    (
+                        "GOOSE_AUTH_TOKEN_URL",
+                        Some("https://gitlab.mycorp.com/oauth/token"),
+                    ),
+                    (
+                        "GOOSE_AUTH_DEVICE_URL",
+                        Some("https://gitlab.mycorp.com/oauth/authorize_device"),
+                    ),
+                ],
+            ]
+            .concat(),
+            Provider::resolve(Some("gitlab")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            gitlab.device_authorization_url.as_deref(),
+            Some("https://gitlab.mycorp.com/oauth/authorize_device")
+        );
+        assert_eq!(
+            Provider::github_enterprise("github.mycorp.com")
+                .device_authorization_url
+                .as_deref(),
+            Some("https://github.mycorp.com/login/device/code")
+        );
+    }
+
+    #[test]
+    fn test_provider_detection() {
+        assert_eq!(
+            detect_from(Some("github.mycorp.com"), None).map(|(name, _)| name),
+            Some("github")
+        );
+        for (redirect, expected) in [
+            ("https://gitlab.com/oauth_callback", Some("gitlab")),
+            ("https://auth.gitlab.com/cb", Some("gitlab")),
+            ("https://gitlab.mycorp.com/cb", None),
+            ("https://github.mycorp.com/cb", None),
+            ("https://notgitlab.com/cb", None),
+            ("http://127.0.0.1:8080/oauth_callback", None),
+        ] {
+            assert_eq!(
+                detect_from(None, Some(redirect)).map(|(name, _)| name),
+                expected,
+                "{}",
+                redirect
+            );
+        }
+        assert_eq!(
+            self_hosted_guess("https://gitlab.mycorp.com/cb"),
+            Some(("gitlab", "gitlab.mycorp.com".to_string()))
+        );
+        assert_eq!(
+            self_hosted_guess("https://github.mycorp.com/cb").map(|(name, _)| name),
+            Some("github")
+        );
+        assert_eq!(self_hosted_guess("https://notgitlab.com/cb"), None);
+    }
+
+    #[tokio::test]
+    async fn test_gitlab_preset() {
+        let gitlab = Provider::by_name("gitlab").unwrap();
+        assert_eq!(gitlab.token_url, "https://gitlab.com/oauth/token");
+        assert_eq!(gitlab.login_field, "username");
+        assert_eq!(
+            Provider::from_token_key(&gitlab.token_key("work"))
+                .await
+                .unwrap(),
+            gitlab
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_provider_requires_endpoints() {
+        let err = Provider::resolve(Some("custom")).await.unwrap_err();
+        assert!(err.to_string().contains("GOOSE_AUTH_AUTHORIZE_URL"));
+    }
+
+    #[test]
+    fn test_token_key_is_namespaced_per_provider() {
+        // Existing tokens were stored under this key before providers existed
+        assert_eq!(Provider::github().token_key("default"), "github:default");
+        assert_eq!(Provider::github().token_key("work"), "github:work");
+    }
+
+    #[test]
+    fn test_github_host_is_validated() {
+        assert_eq!(parse_github_host("").unwrap(), None);
+        assert_eq!(parse_github_host("github.com").unwrap(), None);
+        assert_eq!(
+            parse_github_host(" GitHub.MyCorp.com/ ")
+                .unwrap()
+                .as_deref(),
+            Some("github.mycorp.com")
+        );
+        assert_eq!(
+            parse_github_host("ghe.example.com:8443")
+                .unwrap()
+                .as_deref(),
+            Some("ghe.example.com:8443")
+        );
+        for bad in [
+            "https://ghe.example.com",
+            "ghe.example.com/api",
+            "user@ghe",
+            "a b",
+        ] {
+            assert!(parse_github_host(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_github_enterprise_endpoints() {
+        let provider = Provider::github_enterprise("github.mycorp.com");
+        assert_eq!(
+            provider.authorize_url,
+            "https://github.mycorp.com/login/oauth/authorize"
+        );
+        assert_eq!(provider.user_url, "https://github.mycorp.com/api/v3/user");
+        assert_eq!(provider.token_key("default"), "github:default");
+    }
+
+    #[test]
+    fn test_oidc_default_scopes_follow_discovery() {
+        let discovery = Discovery {
+            issuer: "https://login.example.com".to_string(),
+            authorization_endpoint: "https://login.example.com/authorize".to_string(),
+            token_endpoint: "https://login.example.com/token".to_string(),
+            userinfo_endpoint: Some("https://login.example.com/userinfo".to_string()),
+            scopes_supported: vec!["openid".to_string(), "email".to_string()],
+            jwks_uri: Some("https://login.example.com/jwks".to_string()),
+            device_authorization_endpoint: None,
+        };
+        let provider = Provider::oidc(&discovery);
+        assert_eq!(provider.default_scopes, "openid email");
+        assert_eq!(provider.user_url, "https://login.example.com/userinfo");
+        assert_eq!(
+            provider.id_token.unwrap().jwks_uri.as_deref(),
+            Some("https://login.example.com/jwks")
+        );
+    }
+}
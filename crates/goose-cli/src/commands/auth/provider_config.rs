@@ -0,0 +1,195 @@
+//! Fully custom providers defined in a JSON file, for identity providers no
+//! preset covers. `--provider-config` or `GOOSE_AUTH_PROVIDER_CONFIG` names
+//! the file:
+//!
+//! ```json
+//! {
+//!   "authorize_url": "https://idp.example.com/oauth2/authorize",
+//!   "token_url": "https://idp.example.com/oauth2/token",
+//!   "userinfo_url": "https://idp.example.com/oauth2/userinfo",
+//!   "scopes": "openid email",
+//!   "uses_pkce": true,
+//!   "client_auth_method": "client_secret_basic"
+//! }
+//! ```
+//!
+//! The definition becomes the `custom` provider, used when no other provider
+//! is named, and is taken as written: none of the endpoint overrides apply.
+//! `login_field` (default `login`) names the userinfo field holding the user,
+//! `uses_pkce` defaults to true and `client_auth_method` to
+//! `client_secret_post`. `device_authorization_url`, if given, enables the
+//! device flow.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+
+use super::config::FileScopes;
+use super::provider::{ClientAuthMethod, Provider};
+use super::scopes::ScopeDelimiter;
+
+/// `--provider-config` for this invocation
+static FLAG: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProviderFile {
+    authorize_url: String,
+    token_url: String,
+    userinfo_url: String,
+    #[serde(default)]
+    login_field: Option<String>,
+    #[serde(default)]
+    scopes: Option<FileScopes>,
+    #[serde(default = "default_uses_pkce")]
+    uses_pkce: bool,
+    #[serde(default)]
+    client_auth_method: ClientAuthMethod,
+    #[serde(default)]
+    device_authorization_url: Option<String>,
+}
+
+fn default_uses_pkce() -> bool {
+    true
+}
+
+/// Remember `--provider-config` for [`load`].
+pub fn configure(flag: Option<&Path>) {
+    *FLAG.lock().unwrap() = flag.map(Path::to_path_buf);
+}
+
+/// The provider defined by `--provider-config`, else
+/// `GOOSE_AUTH_PROVIDER_CONFIG`, or `None` when neither is given.
+pub fn load() -> Result<Option<Provider>> {
+    let path = FLAG.lock().unwrap().clone().or_else(|| {
+        std::env::var("GOOSE_AUTH_PROVIDER_CONFIG")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| PathBuf::from(path.trim()))
+    });
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Provider config {} cannot be read: {}", path.display(), e))?;
+    parse(&contents)
+        .map(Some)
+        .map_err(|e| anyhow!("Invalid provider config {}: {}", path.display(), e))
+}
+
+fn parse(contents: &str) -> Result<Provider> {
+    let file: ProviderFile = serde_json::from_str(contents)?;
+    for (field, url) in [
+        ("authorize_url", &file.authorize_url),
+        ("token_url", &file.token_url),
+        ("userinfo_url", &file.userinfo_url),
+    ] {
+        check_url(field, url)?;
+    }
+    if let Some(url) = &file.device_authorization_url {
+        check_url("device_authorization_url", url)?;
+    }
+    let login_field = file.login_field.unwrap_or_else(|| "login".to_string());
+    if login_field.trim().is_empty() {
+        return Err(anyhow!("login_field is empty"));
+    }
+    Ok(Provider {
+        name: "custom".to_string(),
+        authorize_url: file.authorize_url,
+        token_url: file.token_url,
+        user_url: file.userinfo_url,
+        login_field,
+        default_scopes: file
+            .scopes
+            .as_ref()
+            .map(FileScopes::joined)
+            .unwrap_or_default(),
+        scope_delimiter: ScopeDelimiter::Space,
+        id_token: None,
+        uses_pkce: file.uses_pkce,
+        client_auth_method: file.client_auth_method,
+        device_authorization_url: file.device_authorization_url,
+    })
+}
+
+// Absolute https URLs, or http on loopback for local development servers.
+fn check_url(field: &str, raw: &str) -> Result<()> {
+    let url = Url::parse(raw.trim())
+        .map_err(|e| anyhow!("{} '{}' is not a valid URL: {}", field, raw, e))?;
+    let loopback = matches!(
+        url.host_str(),
+        Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+    );
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        "http" => Err(anyhow!(
+            "{} '{}' must use https unless it points at localhost",
+            field,
+            raw
+        )),
+        other => Err(anyhow!(
+            "{} '{}' has unsupported scheme '{}'",
+            field,
+            raw,
+            other
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_config_is_used_verbatim() {
+        let provider = parse(
+            r#"{
+                "authorize_url": "https://idp.example.com/authorize",
+                "token_url": "https://idp.example.com/token",
+                "userinfo_url": "http://localhost:9000/userinfo",
+                "scopes": ["openid", "email"],
+                "uses_pkce": false,
+                "client_auth_method": "client_secret_basic"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(provider.name, "custom");
+        assert_eq!(provider.user_url, "http://localhost:9000/userinfo");
+        assert_eq!(provider.default_scopes, "openid email");
+        assert!(!provider.uses_pkce);
+        assert_eq!(
+            provider.client_auth_method,
+            ClientAuthMethod::ClientSecretBasic
+        );
+    }
+
+    #[test]
+    fn test_invalid_provider_config_is_rejected() {
+        // Missing token_url
+        assert!(parse(
+            r#"{"authorize_url": "https://idp.example.com/a", "userinfo_url": "https://idp.example.com/u"}"#
+        )
+        .is_err());
+        let err = parse(
+            r#"{
+                "authorize_url": "http://idp.example.com/authorize",
+                "token_url": "https://idp.example.com/token",
+                "userinfo_url": "https://idp.example.com/userinfo"
+            }"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("must use https"), "{}", err);
+        assert!(parse(
+            r#"{
+                "authorize_url": "https://idp.example.com/authorize",
+                "token_url": "https://idp.example.com/token",
+                "userinfo_url": "https://idp.example.com/userinfo",
+                "client_auth_method": "private_key_jwt"
+            }"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//! `GOOSE_AUTH_QR=1` prints the URL to open as a QR code as well, so a login
+//! on a remote or headless host can be finished from a phone instead of
+//! copying the URL by hand. Rendering needs the `qr` cargo feature.
+
+use anyhow::Result;
+
+use super::output::say;
+
+fn enabled() -> bool {
+    std::env::var("GOOSE_AUTH_QR").as_deref() == Ok("1")
+}
+
+/// Print `url` as a QR code when `GOOSE_AUTH_QR=1`. A failure only costs the
+/// code; the URL itself has already been printed.
+pub fn print(url: &str) {
+    if !enabled() {
+        return;
+    }
+    match render(url) {
+        Ok(code) => say!("{}", code),
+        Err(e) => eprintln!("[oauth-info] Cannot show a QR code: {}", e),
+    }
+}
+
+#[cfg(feature = "qr")]
+fn render(url: &str) -> Result<String> {
+    use qrcode::render::unicode::Dense1x2;
+
+    let code = qrcode::QrCode::new(url.as_bytes())?;
+    // Inverted so the code scans on a dark terminal background
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+#[cfg(not(feature = "qr"))]
+fn render(_url: &str) -> Result<String> {
+    Err(anyhow::anyhow!(
+        "this goose was built without the `qr` feature"
+    ))
+}
+
+#[cfg(all(test, feature = "qr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_is_square_block_art() {
+        let code = render("https://github.com/login/device").unwrap();
+        let lines: Vec<&str> = code.lines().collect();
+        assert!(lines.len() > 10);
+        let width = lines[0].chars().count();
+        assert!(lines.iter().all(|line| line.chars().count() == width));
+    }
+}
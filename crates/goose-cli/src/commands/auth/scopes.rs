@@ -0,0 +1,375 @@
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Split a scope list on whitespace and commas, dropping empty entries and
+/// duplicates while keeping the first-seen order.
+pub fn normalize_scopes(raw: &str) -> Vec<String> {
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in raw.split(|c: char| c.is_whitespace() || c == ',') {
+        let scope = scope.trim();
+        if !scope.is_empty() && !scopes.iter().any(|s| s == scope) {
+            scopes.push(scope.to_string());
+        }
+    }
+    scopes
+}
+
+/// Parse the contents of a scopes file: one or more scopes per line, separated
+/// by whitespace or commas, with `#` starting a comment that runs to end of line.
+pub fn parse_scopes_file(contents: &str) -> Vec<String> {
+    let stripped: Vec<&str> = contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .collect();
+    normalize_scopes(&stripped.join("\n"))
+}
+
+pub fn load_scopes_file(path: &Path) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read scopes file {}", path.display()))?;
+    let scopes = parse_scopes_file(&contents);
+    if scopes.is_empty() {
+        return Err(anyhow!("Scopes file {} lists no scopes", path.display()));
+    }
+    Ok(scopes)
+}
+
+// RFC 6749 section 3.3: scope-token = 1*( %x21 / %x23-5B / %x5D-7E )
+fn validate_scopes(scopes: &[String]) -> Result<()> {
+    for scope in scopes {
+        let valid = scope
+            .chars()
+            .all(|c| matches!(c, '\x21' | '\x23'..='\x5B' | '\x5D'..='\x7E'));
+        if !valid {
+            return Err(anyhow!("Invalid scope {:?}", scope));
+        }
+    }
+    Ok(())
+}
+
+/// Resolve the scopes to request.
+///
+/// Precedence: `--scopes` flag > scopes file (`--scopes-file` or
+/// `GOOSE_GITHUB_SCOPES_FILE`) > `GOOSE_GITHUB_SCOPES` (or `scopes` in
+/// auth.toml) > the provider's `defaults`. Whichever wins,
+/// `GOOSE_GITHUB_EXTRA_SCOPES` is added on top.
+pub fn resolve_scopes(
+    flag: Option<&str>,
+    scopes_file: Option<&Path>,
+    defaults: &str,
+) -> Result<Vec<String>> {
+    let env_file = std::env::var("GOOSE_GITHUB_SCOPES_FILE")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .map(PathBuf::from);
+
+    let scopes = if let Some(flag) = flag {
+        normalize_scopes(flag)
+    } else if let Some(path) = scopes_file.map(Path::to_path_buf).or(env_file) {
+        load_scopes_file(&path)?
+    } else if let Some(env) = super::config::setting("GOOSE_GITHUB_SCOPES")? {
+        normalize_scopes(&env)
+    } else {
+        normalize_scopes(defaults)
+    };
+    let scopes = with_extra_scopes(
+        scopes,
+        std::env::var("GOOSE_GITHUB_EXTRA_SCOPES").ok().as_deref(),
+    );
+
+    validate_scopes(&scopes)?;
+    tracing::debug!(scopes = %scopes.join(" "), "scopes normalized");
+    Ok(scopes)
+}
+
+// Extra scopes extend the base list instead of replacing it, so asking for
+// `workflow` does not mean retyping the defaults.
+fn with_extra_scopes(mut scopes: Vec<String>, extra: Option<&str>) -> Vec<String> {
+    for scope in extra.map(normalize_scopes).unwrap_or_default() {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    scopes
+}
+
+/// Scopes GitHub documents for OAuth apps. Anything else is passed through
+/// with a warning, since GitHub adds scopes from time to time.
+const KNOWN_GITHUB_SCOPES: &[&str] = &[
+    "repo",
+    "repo:status",
+    "repo_deployment",
+    "public_repo",
+    "repo:invite",
+    "security_events",
+    "admin:repo_hook",
+    "write:repo_hook",
+    "read:repo_hook",
+    "admin:org",
+    "write:org",
+    "read:org",
+    "manage_runners:org",
+    "admin:public_key",
+    "write:public_key",
+    "read:public_key",
+    "admin:org_hook",
+    "gist",
+    "notifications",
+    "user",
+    "read:user",
+    "user:email",
+    "user:follow",
+    "project",
+    "read:project",
+    "delete_repo",
+    "write:packages",
+    "read:packages",
+    "delete:packages",
+    "admin:gpg_key",
+    "write:gpg_key",
+    "read:gpg_key",
+    "codespace",
+    "workflow",
+    "admin:enterprise",
+    "manage_runners:enterprise",
+    "manage_billing:enterprise",
+    "read:enterprise",
+    "audit_log",
+    "read:audit_log",
+    "copilot",
+    "manage_billing:copilot",
+    "admin:ssh_signing_key",
+    "write:ssh_signing_key",
+    "read:ssh_signing_key",
+    "write:discussion",
+    "read:discussion",
+];
+
+/// Scopes GitHub does not document, most likely typos.
+pub fn unknown_github_scopes(scopes: &[String]) -> Vec<String> {
+    scopes
+        .iter()
+        .filter(|scope| !KNOWN_GITHUB_SCOPES.contains(&scope.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// How scopes are joined in the authorize request's `scope` parameter. OAuth 2
+/// specifies spaces, which GitHub and OIDC providers use, but some providers
+/// expect commas or plus signs and silently grant nothing otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeDelimiter {
+    Space,
+    Comma,
+    Plus,
+}
+
+impl ScopeDelimiter {
+    /// Accepts `space`, `comma` and `plus` or the delimiter itself.
+    pub fn parse(raw: &str) -> Result<Self> {
+        if raw == " " {
+            return Ok(Self::Space);
+        }
+        match raw.trim().to_lowercase().as_str() {
+            "space" => Ok(Self::Space),
+            "comma" | "," => Ok(Self::Comma),
+            "plus" | "+" => Ok(Self::Plus),
+            _ => Err(anyhow!(
+                "Invalid scope delimiter {:?}, expected one of: space, comma, plus",
+                raw
+            )),
+        }
+    }
+
+    /// `GOOSE_AUTH_SCOPE_DELIMITER` when set, otherwise `default`.
+    pub fn from_env(default: Self) -> Result<Self> {
+        match std::env::var("GOOSE_AUTH_SCOPE_DELIMITER") {
+            Ok(raw) => Self::parse(&raw),
+            Err(_) => Ok(default),
+        }
+    }
+
+    pub fn join(self, scopes: &[String]) -> String {
+        let delimiter = match self {
+            Self::Space => " ",
+            Self::Comma => ",",
+            Self::Plus => "+",
+        };
+        scopes.join(delimiter)
+    }
+}
+
+/// Some IdPs only grant the scopes named in the latest request, so a re-login
+/// that asks for new scopes would silently drop the old ones. Unless `exact`
+/// is set, keep everything previously granted and add the new scopes after it.
+pub fn merge_scopes(previous: Option<&[String]>, requested: &[String], exact: bool) -> Vec<String> {
+    let previous = match previous {
+        Some(prev) if !exact => prev,
+        _ => return requested.to_vec(),
+    };
+    let mut merged = previous.to_vec();
+    for scope in requested {
+        if !merged.contains(scope) {
+            merged.push(scope.clone());
+        }
+    }
+    merged
+}
+
+/// GitHub scopes that include narrower ones, e.g. a `repo` grant covers a
+/// `public_repo` request. Only the direct children are listed; [`covers`]
+/// follows the chain.
+const IMPLIED_SCOPES: &[(&str, &[&str])] = &[
+    (
+        "repo",
+        &[
+            "repo:status",
+            "repo_deployment",
+            "public_repo",
+            "repo:invite",
+            "security_events",
+        ],
+    ),
+    ("admin:org", &["write:org", "manage_runners:org"]),
+    ("write:org", &["read:org"]),
+    ("admin:public_key", &["write:public_key"]),
+    ("write:public_key", &["read:public_key"]),
+    ("admin:repo_hook", &["write:repo_hook"]),
+    ("write:repo_hook", &["read:repo_hook"]),
+    ("admin:gpg_key", &["write:gpg_key"]),
+    ("write:gpg_key", &["read:gpg_key"]),
+    ("user", &["read:user", "user:email", "user:follow"]),
+    ("write:packages", &["read:packages"]),
+    ("project", &["read:project"]),
+];
+
+fn covers(granted: &str, scope: &str) -> bool {
+    granted == scope
+        || IMPLIED_SCOPES
+            .iter()
+            .filter(|(parent, _)| *parent == granted)
+            .flat_map(|(_, children)| children.iter())
+            .any(|child| covers(child, scope))
+}
+
+/// Requested scopes that no granted scope covers, in request order.
+pub fn missing_scopes(requested: &[String], granted: &[String]) -> Vec<String> {
+    requested
+        .iter()
+        .filter(|scope| !granted.iter().any(|g| covers(g, scope)))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_normalize_scopes_mixed_separators() {
+        assert_eq!(
+            normalize_scopes("repo, read:user  repo,,workflow"),
+            vec!["repo", "read:user", "workflow"]
+        );
+    }
+
+    #[test]
+    fn test_scopes_file_with_comments_and_blank_lines() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "# scopes required by this project").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "read:user user:email   # identity").unwrap();
+        writeln!(file, "   ").unwrap();
+        writeln!(file, "repo,workflow").unwrap();
+        writeln!(file, "# trailing comment").unwrap();
+
+        let scopes = load_scopes_file(file.path()).unwrap();
+        assert_eq!(scopes, vec!["read:user", "user:email", "repo", "workflow"]);
+    }
+
+    #[test]
+    fn test_scopes_file_without_scopes_is_rejected() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "# nothing here").unwrap();
+        assert!(load_scopes_file(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_flag_takes_precedence_over_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "repo").unwrap();
+        let scopes = resolve_scopes(Some("gist"), Some(file.path()), "read:user").unwrap();
+        assert_eq!(scopes, vec!["gist"]);
+    }
+
+    #[test]
+    fn test_extra_scopes_extend_base() {
+        let base = normalize_scopes("read:user user:email");
+        assert_eq!(
+            with_extra_scopes(base.clone(), Some("repo,workflow read:user")),
+            vec!["read:user", "user:email", "repo", "workflow"]
+        );
+        assert_eq!(with_extra_scopes(base.clone(), None), base);
+    }
+
+    #[test]
+    fn test_unknown_github_scopes() {
+        let scopes = normalize_scopes("repo workflow raed:user");
+        assert_eq!(unknown_github_scopes(&scopes), vec!["raed:user"]);
+    }
+
+    #[test]
+    fn test_invalid_scope_is_rejected() {
+        assert!(resolve_scopes(Some("repo \"bad\""), None, "read:user").is_err());
+    }
+
+    #[test]
+    fn test_missing_scopes_allow_broader_grants() {
+        let granted = normalize_scopes("repo, admin:org, user");
+        let requested = normalize_scopes("public_repo read:org user:email workflow");
+        assert_eq!(missing_scopes(&requested, &granted), vec!["workflow"]);
+    }
+
+    #[test]
+    fn test_scope_delimiter_parse() {
+        assert_eq!(
+            ScopeDelimiter::parse("space").unwrap(),
+            ScopeDelimiter::Space
+        );
+        assert_eq!(ScopeDelimiter::parse(" ").unwrap(), ScopeDelimiter::Space);
+        assert_eq!(
+            ScopeDelimiter::parse("Comma").unwrap(),
+            ScopeDelimiter::Comma
+        );
+        assert_eq!(ScopeDelimiter::parse("+").unwrap(), ScopeDelimiter::Plus);
+        assert!(ScopeDelimiter::parse(";").is_err());
+        assert!(ScopeDelimiter::parse("").is_err());
+    }
+
+    #[test]
+    fn test_merge_keeps_previously_granted_scopes() {
+        let previous = vec!["read:user".to_string(), "repo".to_string()];
+        let requested = vec!["read:user".to_string(), "workflow".to_string()];
+        assert_eq!(
+            merge_scopes(Some(&previous), &requested, false),
+            vec!["read:user", "repo", "workflow"]
+        );
+    }
+
+    #[test]
+    fn test_merge_exact_narrows_to_requested() {
+        let previous = vec!["read:user".to_string(), "repo".to_string()];
+        let requested = vec!["read:user".to_string()];
+        assert_eq!(
+            merge_scopes(Some(&previous), &requested, true),
+            vec!["read:user"]
+        );
+    }
+
+    #[test]
+    fn test_merge_without_previous_token() {
+        let requested = vec!["gist".to_string()];
+        assert_eq!(merge_scopes(None, &requested, false), vec!["gist"]);
+    }
+}
@@ -0,0 +1,198 @@
+//! Encrypted state files for in-flight secrets such as a device flow's
+//! `device_code` or a paused login's `code_verifier`.
+//!
+//! Contents are sealed with AES-256-GCM. The key is derived from
+//! `GOOSE_TOKEN_PASSPHRASE` when it is set, otherwise it is a random machine key
+//! kept next to the state files. A file sealed with a passphrase can only be
+//! opened with that passphrase, and nothing is ever written in plaintext.
+
+use anyhow::{anyhow, Context, Result};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use super::token_store::{self, write_atomic};
+
+const MAGIC: &[u8; 4] = b"GST1";
+const MODE_PASSPHRASE: u8 = 1;
+const MODE_MACHINE: u8 = 2;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+/// OWASP's recommended work factor for PBKDF2-HMAC-SHA256
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Where the sealing key comes from.
+pub enum StateKey {
+    Passphrase(String),
+    /// Random key stored at this path, created on first use
+    Machine(PathBuf),
+}
+
+impl StateKey {
+    /// `GOOSE_TOKEN_PASSPHRASE`, or the machine key in the auth config dir.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("GOOSE_TOKEN_PASSPHRASE") {
+            Ok(passphrase) if !passphrase.is_empty() => Ok(Self::Passphrase(passphrase)),
+            _ => Ok(Self::Machine(token_store::auth_dir()?.join("state.key"))),
+        }
+    }
+
+    fn mode(&self) -> u8 {
+        match self {
+            Self::Passphrase(_) => MODE_PASSPHRASE,
+            Self::Machine(_) => MODE_MACHINE,
+        }
+    }
+
+    fn key_bytes(&self, salt: &[u8], create: bool) -> Result<[u8; KEY_LEN]> {
+        let mut key = [0u8; KEY_LEN];
+        match self {
+            Self::Passphrase(passphrase) => pbkdf2::derive(
+                pbkdf2::PBKDF2_HMAC_SHA256,
+                NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero iterations"),
+                salt,
+                passphrase.as_bytes(),
+                &mut key,
+            ),
+            Self::Machine(path) => key = machine_key(path, create)?,
+        }
+        Ok(key)
+    }
+}
+
+fn machine_key(path: &Path, create: bool) -> Result<[u8; KEY_LEN]> {
+    match std::fs::read(path) {
+        Ok(bytes) => bytes
+            .try_into()
+            .map_err(|_| anyhow!("Machine key {} is corrupt", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => {
+            let mut key = [0u8; KEY_LEN];
+            random(&mut key)?;
+            write_atomic(path, &key)?;
+            Ok(key)
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to read machine key {}", path.display())),
+    }
+}
+
+fn random(buf: &mut [u8]) -> Result<()> {
+    SystemRandom::new()
+        .fill(buf)
+        .map_err(|_| anyhow!("System random number generator failed"))
+}
+
+fn cipher(key: &[u8; KEY_LEN]) -> LessSafeKey {
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).expect("AES-256 key length"))
+}
+
+/// Encrypt `plaintext`; the header (format, key mode, salt, nonce) is
+/// authenticated along with it.
+pub fn seal(key: &StateKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(key.mode());
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    random(&mut salt)?;
+    random(&mut nonce)?;
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let mut sealed = plaintext.to_vec();
+    cipher(&key.key_bytes(&salt, true)?)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(&header),
+            &mut sealed,
+        )
+        .map_err(|_| anyhow!("Failed to encrypt state"))?;
+    header.extend_from_slice(&sealed);
+    Ok(header)
+}
+
+pub fn open(key: &StateKey, sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < HEADER_LEN || &sealed[..MAGIC.len()] != MAGIC {
+        return Err(anyhow!("Not an encrypted goose state file"));
+    }
+    let (header, ciphertext) = sealed.split_at(HEADER_LEN);
+    let mode = header[MAGIC.len()];
+    if mode != key.mode() {
+        return Err(if mode == MODE_PASSPHRASE {
+            anyhow!(
+                "This state was encrypted with a passphrase; set GOOSE_TOKEN_PASSPHRASE to open it"
+            )
+        } else {
+            anyhow!("This state was encrypted with the machine key; unset GOOSE_TOKEN_PASSPHRASE to open it")
+        });
+    }
+    let salt = &header[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let nonce: [u8; NONCE_LEN] = header[HEADER_LEN - NONCE_LEN..]
+        .try_into()
+        .expect("nonce length");
+
+    let mut buf = ciphertext.to_vec();
+    let plaintext = cipher(&key.key_bytes(salt, false)?)
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(header),
+            &mut buf,
+        )
+        .map_err(|_| anyhow!("Failed to decrypt state: wrong passphrase or corrupt file"))?;
+    Ok(plaintext.to_vec())
+}
+
+/// Seal `value` as JSON and write it with `0600` permissions. Refuses to write
+/// anything under `--no-persist`.
+pub fn write_state<T: Serialize>(key: &StateKey, path: &Path, value: &T) -> Result<()> {
+    if token_store::persistence_disabled() {
+        return Err(anyhow!("Not saving login state: persistence is disabled"));
+    }
+    write_atomic(path, &seal(key, &serde_json::to_vec(value)?)?)
+}
+
+pub fn read_state<T: DeserializeOwned>(key: &StateKey, path: &Path) -> Result<Option<T>> {
+    let sealed = match std::fs::read(path) {
+        Ok(sealed) => sealed,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let plaintext = open(key, &sealed).with_context(|| path.display().to_string())?;
+    Ok(Some(serde_json::from_slice(&plaintext)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_state_file_does_not_contain_plaintext_verifier() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = StateKey::Machine(dir.path().join("state.key"));
+        let path = dir.path().join("login.state");
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+        write_state(&key, &path, &json!({"code_verifier": verifier})).unwrap();
+        let on_disk = std::fs::read(&path).unwrap();
+        assert!(!on_disk
+            .windows(verifier.len())
+            .any(|window| window == verifier.as_bytes()));
+
+        let state: Value = read_state(&key, &path).unwrap().unwrap();
+        assert_eq!(state["code_verifier"], verifier);
+    }
+
+    #[test]
+    fn test_passphrase_state_needs_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let sealed = seal(&StateKey::Passphrase("correct horse".into()), b"secret").unwrap();
+        let err = open(&StateKey::Machine(dir.path().join("state.key")), &sealed).unwrap_err();
+        assert!(err.to_string().contains("GOOSE_TOKEN_PASSPHRASE"));
+        assert!(open(&StateKey::Passphrase("wrong".into()), &sealed).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::id_token::IdTokenClaims;
+use super::scopes::normalize_scopes;
+use super::token_store::TokenStore;
+
+/// Everything we keep about a token obtained through `goose auth login`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenSet {
+    /// The access token used to authenticate API requests
+    pub access_token: String,
+
+    /// Scopes the provider reported as granted
+    #[serde(default)]
+    pub scopes: Vec<String>,
+
+    /// When the access token expires, if the provider said
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Exchanged for a new access token once this one is about to expire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+
+    /// When the refresh token itself stops working, if the provider said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_expires_at: Option<DateTime<Utc>>,
+
+    /// Verified id_token claims, for OIDC logins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_claims: Option<IdTokenClaims>,
+}
+
+/// Refresh this long before the access token actually expires
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+fn expiry(json: &Value, field: &str) -> Option<DateTime<Utc>> {
+    json.get(field)
+        .and_then(Value::as_i64)
+        .map(|secs| Utc::now() + Duration::seconds(secs))
+}
+
+impl TokenSet {
+    /// Build from a token endpoint response. GitHub reports the granted scopes as
+    /// a comma-separated `scope` field; when absent the requested scopes are assumed.
+    pub fn from_response(json: &Value, access_token: &str, requested_scopes: &[String]) -> Self {
+        let scopes = json
+            .get("scope")
+            .and_then(|v| v.as_str())
+            .map(normalize_scopes)
+            .unwrap_or_else(|| requested_scopes.to_vec());
+        Self {
+            access_token: access_token.to_string(),
+            scopes,
+            expires_at: expiry(json, "expires_in"),
+            refresh_token: json
+                .get("refresh_token")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            refresh_expires_at: expiry(json, "refresh_token_expires_in"),
+            id_claims: None,
+        }
+    }
+
+    /// Whether the access token expires within the refresh margin.
+    pub fn needs_refresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at - now <= Duration::seconds(REFRESH_MARGIN_SECS))
+    }
+
+    /// The refresh token, unless it is known to have expired.
+    pub fn usable_refresh_token(&self, now: DateTime<Utc>) -> Option<&str> {
+        if self
+            .refresh_expires_at
+            .is_some_and(|expires_at| expires_at <= now)
+        {
+            return None;
+        }
+        self.refresh_token.as_deref()
+    }
+
+    /// Whether the provider-reported expiry has passed. Tokens without an
+    /// expiry never count as expired.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Parse a stored value. Older entries held just the raw token string.
+    pub fn parse(stored: &str) -> Self {
+        serde_json::from_str(stored).unwrap_or_else(|_| Self {
+            access_token: stored.trim().to_string(),
+            scopes: Vec::new(),
+            expires_at: None,
+            refresh_token: None,
+            refresh_expires_at: None,
+            id_claims: None,
+        })
+    }
+}
+
+pub fn load_token_set(store: &dyn TokenStore, key: &str) -> Result<Option<TokenSet>> {
+    Ok(store.load(key)?.map(|stored| TokenSet::parse(&stored)))
+}
+
+pub fn store_token_set(store: &dyn TokenStore, key: &str, set: &TokenSet) -> Result<()> {
+    store.store(key, &serde_json::to_string(set)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_refresh_fields_from_response() {
+        let json = json!({
+            "access_token": "ghu_new",
+            "expires_in": 28800,
+            "refresh_token": "ghr_refresh",
+            "refresh_token_expires_in": 15897600
+        });
+        let set = TokenSet::from_response(&json, "ghu_new", &[]);
+        let now = Utc::now();
+        assert!(!set.needs_refresh(now));
+        assert!(set.needs_refresh(now + Duration::seconds(28800 - 30)));
+        assert_eq!(set.usable_refresh_token(now), Some("ghr_refresh"));
+        assert_eq!(set.usable_refresh_token(now + Duration::days(365)), None);
+    }
+}
@@ -0,0 +1,156 @@
+//! Handing the access token to a parent process without printing it.
+//!
+//! `GOOSE_TOKEN_FD` names an inherited file descriptor and `GOOSE_TOKEN_PIPE`
+//! an existing named pipe. When either is set, a successful login and
+//! `goose auth token` write the token there, followed by a newline, and close
+//! it straight away so the reader sees EOF; the token never goes to stdout.
+//! Opening a named pipe waits until the other end is opened for reading.
+
+use anyhow::{anyhow, Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Where the token goes instead of stdout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenSink {
+    Fd(i32),
+    Pipe(PathBuf),
+}
+
+impl TokenSink {
+    /// The sink from `GOOSE_TOKEN_FD` or `GOOSE_TOKEN_PIPE`, if one is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let var = |name| {
+            std::env::var(name)
+                .ok()
+                .map(|value: String| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        Self::parse(
+            var("GOOSE_TOKEN_FD").as_deref(),
+            var("GOOSE_TOKEN_PIPE").as_deref(),
+        )
+    }
+
+    fn parse(fd: Option<&str>, pipe: Option<&str>) -> Result<Option<Self>> {
+        match (fd, pipe) {
+            (Some(_), Some(_)) => Err(anyhow!(
+                "Set only one of GOOSE_TOKEN_FD and GOOSE_TOKEN_PIPE"
+            )),
+            (Some(fd), None) => {
+                let fd = fd
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|fd| *fd >= 0)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "GOOSE_TOKEN_FD must be a file descriptor number, got '{}'",
+                            fd
+                        )
+                    })?;
+                // The point is to keep the token off the terminal
+                if fd <= 2 {
+                    return Err(anyhow!(
+                        "GOOSE_TOKEN_FD={} is a standard stream; pass a separate descriptor",
+                        fd
+                    ));
+                }
+                Ok(Some(Self::Fd(fd)))
+            }
+            (None, Some(pipe)) => Ok(Some(Self::Pipe(PathBuf::from(pipe)))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Write `token` and close the sink.
+    pub fn send(&self, token: &str) -> Result<()> {
+        let mut file = match self {
+            Self::Fd(fd) => open_fd(*fd)?,
+            Self::Pipe(path) => open_pipe(path)?,
+        };
+        file.write_all(format!("{}\n", token).as_bytes())
+            .and_then(|_| file.flush())
+            .with_context(|| format!("Failed to write the token to {}", self))?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for TokenSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fd(fd) => write!(f, "GOOSE_TOKEN_FD={}", fd),
+            Self::Pipe(path) => write!(f, "GOOSE_TOKEN_PIPE={}", path.display()),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn open_fd(fd: i32) -> Result<std::fs::File> {
+    use std::os::fd::FromRawFd;
+    // Claiming a descriptor that is not open would abort on close
+    std::fs::metadata(format!("/dev/fd/{}", fd))
+        .map_err(|e| anyhow!("GOOSE_TOKEN_FD={} is not an open descriptor: {}", fd, e))?;
+    // SAFETY: the descriptor is open, was handed to this process for the
+    // token alone, and is claimed exactly once, so closing it on drop is ours
+    // to do
+    Ok(unsafe { std::fs::File::from_raw_fd(fd) })
+}
+
+#[cfg(not(unix))]
+fn open_fd(fd: i32) -> Result<std::fs::File> {
+    Err(anyhow!(
+        "GOOSE_TOKEN_FD={} needs Unix file descriptors; use GOOSE_TOKEN_PIPE instead",
+        fd
+    ))
+}
+
+// Only an existing pipe: creating a file here would put the token on disk.
+fn open_pipe(path: &std::path::Path) -> Result<std::fs::File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("GOOSE_TOKEN_PIPE {} cannot be opened", path.display()))?;
+        if !metadata.file_type().is_fifo() {
+            return Err(anyhow!(
+                "GOOSE_TOKEN_PIPE {} is not a named pipe (create one with mkfifo)",
+                path.display()
+            ));
+        }
+    }
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("GOOSE_TOKEN_PIPE {} cannot be opened", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sink_settings() {
+        assert_eq!(TokenSink::parse(None, None).unwrap(), None);
+        assert_eq!(
+            TokenSink::parse(Some("3"), None).unwrap(),
+            Some(TokenSink::Fd(3))
+        );
+        assert!(TokenSink::parse(Some("1"), None).is_err());
+        assert!(TokenSink::parse(Some("three"), None).is_err());
+        assert!(TokenSink::parse(Some("3"), Some("/tmp/token")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_regular_file_is_not_a_pipe() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "").unwrap();
+        let err = TokenSink::Pipe(path.clone()).send("gho_x").unwrap_err();
+        assert!(err.to_string().contains("not a named pipe"), "{}", err);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        assert!(TokenSink::Pipe(dir.path().join("missing"))
+            .send("gho_x")
+            .is_err());
+    }
+}
@@ -0,0 +1,566 @@
+use anyhow::{anyhow, Context, Result};
+use etcetera::{choose_app_strategy, AppStrategy};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const KEYRING_SERVICE: &str = "goose-auth";
+const DEFAULT_KEYRING_TIMEOUT_SECS: u64 = 5;
+const KEYRING_HINT: &str = "unlock it or set GOOSE_TOKEN_STORE=file";
+/// Keychains cannot be enumerated, so the keys goose wrote are tracked here
+const KEYRING_INDEX_ACCOUNT: &str = "goose-auth:index";
+
+/// Set for the lifetime of the process once `--no-persist` is seen.
+static NO_PERSIST: AtomicBool = AtomicBool::new(false);
+
+static MEMORY_STORE: Lazy<Arc<MemoryTokenStore>> =
+    Lazy::new(|| Arc::new(MemoryTokenStore::default()));
+
+/// Disable every token, identity and cache write for the rest of this process.
+/// There is intentionally no way to turn persistence back on.
+pub fn disable_persistence() {
+    NO_PERSIST.store(true, Ordering::SeqCst);
+}
+
+/// True when `--no-persist` or `GOOSE_AUTH_NO_PERSIST=1` is in effect. Anything
+/// that writes auth state to disk or the keychain must check this first.
+pub fn persistence_disabled() -> bool {
+    NO_PERSIST.load(Ordering::SeqCst)
+        || std::env::var("GOOSE_AUTH_NO_PERSIST").unwrap_or_default() == "1"
+}
+
+pub trait TokenStore: Send + Sync {
+    /// Short backend name for user-facing messages
+    fn name(&self) -> &'static str;
+    /// Whether values survive the current process
+    fn is_persistent(&self) -> bool;
+    fn load(&self, key: &str) -> Result<Option<String>>;
+    fn store(&self, key: &str, value: &str) -> Result<()>;
+    fn delete(&self, key: &str) -> Result<()>;
+    /// Keys currently held, in a form `load` and `delete` accept
+    fn list(&self) -> Result<Vec<String>>;
+}
+
+/// Process-local store; the default, and the only store used under `--no-persist`.
+#[derive(Default)]
+pub struct MemoryTokenStore {
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn is_persistent(&self) -> bool {
+        false
+    }
+
+    fn load(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    fn store(&self, key: &str, value: &str) -> Result<()> {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.values.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let mut keys: Vec<String> = self.values.lock().unwrap().keys().cloned().collect();
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// One `0600` file per key under the goose config directory.
+pub struct FileTokenStore {
+    dir: PathBuf,
+}
+
+impl FileTokenStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    // Percent-encoded, so the file name maps back to the exact key.
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.token", urlencoding::encode(key)))
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn is_persistent(&self) -> bool {
+        true
+    }
+
+    fn load(&self, key: &str) -> Result<Option<String>> {
+        match std::fs::read_to_string(self.path_for(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store(&self, key: &str, value: &str) -> Result<()> {
+        write_atomic(&self.path_for(key), value.as_bytes())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        match std::fs::remove_file(self.path_for(key)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // File names decode back to the keys they were stored under.
+    fn list(&self) -> Result<Vec<String>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut keys = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            let key = name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".token"))
+                .and_then(|name| urlencoding::decode(name).ok());
+            if let Some(key) = key {
+                keys.push(key.into_owned());
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// The handful of keychain operations the store needs, so tests can swap in a
+/// locked or hanging keychain.
+pub trait KeyringBackend: Send + Sync + 'static {
+    fn get(&self, account: &str) -> Result<Option<String>>;
+    fn set(&self, account: &str, value: &str) -> Result<()>;
+    fn delete(&self, account: &str) -> Result<()>;
+}
+
+/// The OS keychain (macOS Keychain, Windows Credential Manager, Secret Service).
+pub struct OsKeyring;
+
+impl KeyringBackend for OsKeyring {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        match keyring::Entry::new(KEYRING_SERVICE, account)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, account: &str, value: &str) -> Result<()> {
+        Ok(keyring::Entry::new(KEYRING_SERVICE, account)?.set_password(value)?)
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        match keyring::Entry::new(KEYRING_SERVICE, account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Keychain-backed store. A locked keychain may block on an unlock prompt
+/// indefinitely, so every call runs on a helper thread with a deadline. On
+/// failure the optional fallback store (`GOOSE_TOKEN_STORE_FALLBACK`) is used
+/// instead, otherwise the error explains how to get unstuck.
+pub struct KeyringTokenStore {
+    backend: Arc<dyn KeyringBackend>,
+    timeout: Duration,
+    fallback: Option<Arc<dyn TokenStore>>,
+}
+
+impl KeyringTokenStore {
+    pub fn new(
+        backend: Arc<dyn KeyringBackend>,
+        timeout: Duration,
+        fallback: Option<Arc<dyn TokenStore>>,
+    ) -> Self {
+        Self {
+            backend,
+            timeout,
+            fallback,
+        }
+    }
+
+    fn call<T, F>(&self, op: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn KeyringBackend) -> Result<T> + Send + 'static,
+    {
+        let backend = self.backend.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        // A hung call leaves this thread parked, but never the CLI itself
+        std::thread::spawn(move || {
+            let _ = tx.send(op(backend.as_ref()));
+        });
+        match rx.recv_timeout(self.timeout) {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(e)) => Err(anyhow!("keychain is unavailable ({}); {}", e, KEYRING_HINT)),
+            Err(_) => Err(anyhow!(
+                "keychain is locked or not responding after {}s; {}",
+                self.timeout.as_secs_f32(),
+                KEYRING_HINT
+            )),
+        }
+    }
+
+    fn degrade<T>(
+        &self,
+        err: anyhow::Error,
+        op: impl FnOnce(&dyn TokenStore) -> Result<T>,
+    ) -> Result<T> {
+        match &self.fallback {
+            Some(fallback) => {
+                eprintln!(
+                    "[oauth-info] {}. Using the {} store instead.",
+                    err,
+                    fallback.name()
+                );
+                op(fallback.as_ref())
+            }
+            None => Err(err),
+        }
+    }
+}
+
+impl TokenStore for KeyringTokenStore {
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn is_persistent(&self) -> bool {
+        true
+    }
+
+    fn load(&self, key: &str) -> Result<Option<String>> {
+        let account = key.to_string();
+        self.call(move |backend| backend.get(&account))
+            .or_else(|e| self.degrade(e, |fallback| fallback.load(key)))
+    }
+
+    fn store(&self, key: &str, value: &str) -> Result<()> {
+        let (account, value_owned) = (key.to_string(), value.to_string());
+        self.call(move |backend| {
+            backend.set(&account, &value_owned)?;
+            update_index(backend, |keys| {
+                if !keys.contains(&account) {
+                    keys.push(account.clone());
+                }
+            })
+        })
+        .or_else(|e| self.degrade(e, |fallback| fallback.store(key, value)))
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let account = key.to_string();
+        self.call(move |backend| {
+            backend.delete(&account)?;
+            update_index(backend, |keys| keys.retain(|k| k != &account))
+        })
+        .or_else(|e| self.degrade(e, |fallback| fallback.delete(key)))
+    }
+
+    /// Keys stored through goose; entries written before the index existed
+    /// are not included.
+    fn list(&self) -> Result<Vec<String>> {
+        self.call(read_index)
+            .or_else(|e| self.degrade(e, |fallback| fallback.list()))
+    }
+}
+
+fn read_index(backend: &dyn KeyringBackend) -> Result<Vec<String>> {
+    Ok(backend
+        .get(KEYRING_INDEX_ACCOUNT)?
+        .map(|index| index.lines().map(str::to_string).collect())
+        .unwrap_or_default())
+}
+
+fn update_index(backend: &dyn KeyringBackend, edit: impl FnOnce(&mut Vec<String>)) -> Result<()> {
+    let mut keys = read_index(backend)?;
+    let before = keys.clone();
+    edit(&mut keys);
+    if keys != before {
+        backend.set(KEYRING_INDEX_ACCOUNT, &keys.join("\n"))?;
+    }
+    Ok(())
+}
+
+/// Directory for persisted auth state.
+/// - macOS/Linux: ~/.config/goose/auth
+/// - Windows:     ~\AppData\Roaming\Block\goose\config\auth\
+pub fn auth_dir() -> Result<PathBuf> {
+    Ok(choose_app_strategy(crate::APP_STRATEGY.clone())
+        .context("goose requires a home dir")?
+        .in_config_dir("auth"))
+}
+
+/// Write `contents` to `path` via a temp file and rename so readers never see
+/// a partial file. The file is created with `0600` permissions on Unix.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent directory", path.display()))?;
+    std::fs::create_dir_all(dir)?;
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(contents)?;
+    tmp.as_file().sync_all()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tmp.as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    tmp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Pick a backend by name. `--no-persist` wins over any configured backend.
+pub fn select_store(
+    backend: Option<&str>,
+    no_persist: bool,
+    dir: PathBuf,
+) -> Result<Arc<dyn TokenStore>> {
+    if no_persist {
+        return Ok(MEMORY_STORE.clone());
+    }
+    match backend.map(|b| b.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("memory") | Some("none") => Ok(MEMORY_STORE.clone()),
+        Some("file") => Ok(Arc::new(FileTokenStore::new(dir.join("tokens")))),
+        Some("keyring") => {
+            let fallback_name = std::env::var("GOOSE_TOKEN_STORE_FALLBACK")
+                .ok()
+                .map(|name| name.trim().to_lowercase())
+                .filter(|name| !name.is_empty());
+            let fallback = match fallback_name.as_deref() {
+                Some("keyring") => {
+                    return Err(anyhow!("GOOSE_TOKEN_STORE_FALLBACK cannot be keyring"))
+                }
+                Some(name) => Some(select_store(Some(name), false, dir)?),
+                None => None,
+            };
+            let timeout =
+                super::env_secs("GOOSE_KEYRING_TIMEOUT_SECS", DEFAULT_KEYRING_TIMEOUT_SECS)?;
+            Ok(Arc::new(KeyringTokenStore::new(
+                Arc::new(OsKeyring),
+                timeout,
+                fallback,
+            )))
+        }
+        Some(other) => Err(anyhow!(
+            "Unknown GOOSE_TOKEN_STORE '{}'; expected one of: memory, file, keyring",
+            other
+        )),
+    }
+}
+
+/// The store configured by `GOOSE_TOKEN_STORE` for this invocation.
+pub fn active_store() -> Result<Arc<dyn TokenStore>> {
+    let backend = std::env::var("GOOSE_TOKEN_STORE").ok();
+    select_store(backend.as_deref(), persistence_disabled(), auth_dir()?)
+}
+
+/// Save `token` under `account` in the active store.
+pub fn store_token(account: &str, token: &str) -> Result<()> {
+    active_store()?.store(account, token)
+}
+
+/// Token saved under `account` in the active store, if any.
+pub fn load_token(account: &str) -> Result<Option<String>> {
+    active_store()?.load(account)
+}
+
+/// Remove whatever is saved under `account` in the active store.
+pub fn delete_token(account: &str) -> Result<()> {
+    active_store()?.delete(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_no_persist_writes_nothing() {
+        let dir = TempDir::new().unwrap();
+        let store = select_store(Some("file"), true, dir.path().to_path_buf()).unwrap();
+        assert!(!store.is_persistent());
+
+        store.store("test:no-persist", "secret-token").unwrap();
+        assert_eq!(
+            store.load("test:no-persist").unwrap().as_deref(),
+            Some("secret-token")
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+        store.delete("test:no-persist").unwrap();
+    }
+
+    #[test]
+    fn test_file_store_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let store = select_store(Some("file"), false, dir.path().to_path_buf()).unwrap();
+        assert!(store.is_persistent());
+
+        store.store("github:default", "secret-token").unwrap();
+        assert_eq!(
+            store.load("github:default").unwrap().as_deref(),
+            Some("secret-token")
+        );
+        store.delete("github:default").unwrap();
+        assert_eq!(store.load("github:default").unwrap(), None);
+    }
+
+    struct LockedKeyring;
+
+    impl KeyringBackend for LockedKeyring {
+        fn get(&self, _account: &str) -> Result<Option<String>> {
+            Err(anyhow!("user interaction is not allowed"))
+        }
+
+        fn set(&self, _account: &str, _value: &str) -> Result<()> {
+            Err(anyhow!("user interaction is not allowed"))
+        }
+
+        fn delete(&self, _account: &str) -> Result<()> {
+            Err(anyhow!("user interaction is not allowed"))
+        }
+    }
+
+    struct HangingKeyring;
+
+    impl KeyringBackend for HangingKeyring {
+        fn get(&self, _account: &str) -> Result<Option<String>> {
+            std::thread::sleep(Duration::from_secs(30));
+            Ok(None)
+        }
+
+        fn set(&self, _account: &str, _value: &str) -> Result<()> {
+            std::thread::sleep(Duration::from_secs(30));
+            Ok(())
+        }
+
+        fn delete(&self, _account: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_locked_keyring_reports_how_to_recover() {
+        let store = KeyringTokenStore::new(Arc::new(LockedKeyring), Duration::from_secs(1), None);
+        let err = store.store("test:locked", "secret").unwrap_err();
+        assert!(err.to_string().contains("GOOSE_TOKEN_STORE=file"));
+    }
+
+    #[test]
+    fn test_locked_keyring_falls_back_to_secondary_store() {
+        let fallback: Arc<dyn TokenStore> = Arc::new(MemoryTokenStore::default());
+        let store = KeyringTokenStore::new(
+            Arc::new(LockedKeyring),
+            Duration::from_secs(1),
+            Some(fallback.clone()),
+        );
+        store.store("test:fallback", "secret").unwrap();
+        assert_eq!(
+            fallback.load("test:fallback").unwrap().as_deref(),
+            Some("secret")
+        );
+        assert_eq!(
+            store.load("test:fallback").unwrap().as_deref(),
+            Some("secret")
+        );
+    }
+
+    #[test]
+    fn test_hanging_keyring_times_out() {
+        let store =
+            KeyringTokenStore::new(Arc::new(HangingKeyring), Duration::from_millis(100), None);
+        let started = std::time::Instant::now();
+        let err = store.load("test:hanging").unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.to_string().contains("not responding"));
+    }
+
+    #[test]
+    fn test_file_store_lists_keys() {
+        let dir = TempDir::new().unwrap();
+        let store = FileTokenStore::new(dir.path().to_path_buf());
+        store.store("github:default", "a").unwrap();
+        store.store("gitlab:default", "b").unwrap();
+        let keys = store.list().unwrap();
+        assert_eq!(keys, vec!["github:default", "gitlab:default"]);
+        // Listed keys round-trip through load and delete
+        assert_eq!(store.load(&keys[0]).unwrap().as_deref(), Some("a"));
+        store.delete(&keys[0]).unwrap();
+        assert_eq!(store.list().unwrap(), vec!["gitlab:default"]);
+    }
+
+    #[derive(Default)]
+    struct FakeKeyring(Mutex<HashMap<String, String>>);
+
+    impl KeyringBackend for FakeKeyring {
+        fn get(&self, account: &str) -> Result<Option<String>> {
+            Ok(self.0.lock().unwrap().get(account).cloned())
+        }
+
+        fn set(&self, account: &str, value: &str) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(account.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, account: &str) -> Result<()> {
+            self.0.lock().unwrap().remove(account);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_keyring_store_tracks_keys_in_index() {
+        let store = KeyringTokenStore::new(
+            Arc::new(FakeKeyring::default()),
+            Duration::from_secs(1),
+            None,
+        );
+        store.store("github:default", "a").unwrap();
+        store.store("gitlab:default", "b").unwrap();
+        store.store("github:default", "c").unwrap();
+        assert_eq!(
+            store.list().unwrap(),
+            vec!["github:default", "gitlab:default"]
+        );
+        store.delete("github:default").unwrap();
+        assert_eq!(store.list().unwrap(), vec!["gitlab:default"]);
+    }
+
+    #[test]
+    fn test_unknown_backend_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        assert!(select_store(Some("floppy"), false, dir.path().to_path_buf()).is_err());
+    }
+}
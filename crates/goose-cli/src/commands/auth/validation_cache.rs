@@ -0,0 +1,196 @@
+//! Recently validated tokens, so commands that start with
+//! [`ensure_authenticated`](super::ensure_authenticated) do not each pay a
+//! round-trip to the identity endpoint.
+//!
+//! Every successful identity check records the token's hash and login in the
+//! auth config dir; a stored token checked less than `GOOSE_AUTH_VALIDATE_TTL`
+//! seconds ago (default 300, 0 turns the cache off) is trusted without asking
+//! again. Any 401 for a token drops its entry, so a revoked token is caught
+//! at most one TTL after the last successful check.
+//!
+//! Like the tokens themselves, entries are only kept when the active token
+//! store persists; with the memory store nothing is read or written.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::token_store::{self, write_atomic};
+
+const VALIDATION_CACHE_FILE: &str = "validated.json";
+const DEFAULT_TTL_SECS: i64 = 300;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    login: String,
+    validated_at: DateTime<Utc>,
+}
+
+/// Token hash to the last successful check
+type Entries = HashMap<String, Entry>;
+
+/// The login recorded for `token` if it was validated within the TTL.
+pub fn recent_login(token: &str) -> Result<Option<String>> {
+    let ttl = ttl()?;
+    if ttl.is_zero() || !enabled()? {
+        return Ok(None);
+    }
+    Ok(recent_in(&cache_path()?, token, ttl, Utc::now()))
+}
+
+/// Remember that `token` belongs to `login` as of now.
+pub fn record(token: &str, login: &str) {
+    if let Err(e) = update(|entries, now| {
+        entries.insert(
+            token_hash(token),
+            Entry {
+                login: login.to_string(),
+                validated_at: now,
+            },
+        );
+    }) {
+        tracing::debug!(error = %e, "could not record token validation");
+    }
+}
+
+/// Forget `token`, e.g. after the provider answered 401 for it.
+pub fn forget(token: &str) {
+    if let Err(e) = update(|entries, _| {
+        entries.remove(&token_hash(token));
+    }) {
+        tracing::debug!(error = %e, "could not clear token validation");
+    }
+}
+
+// GOOSE_AUTH_VALIDATE_TTL, in whole seconds
+fn ttl() -> Result<Duration> {
+    match std::env::var("GOOSE_AUTH_VALIDATE_TTL") {
+        Ok(raw) => raw
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|secs| *secs >= 0)
+            .map(Duration::seconds)
+            .ok_or_else(|| {
+                anyhow!(
+                    "GOOSE_AUTH_VALIDATE_TTL must be a whole number of seconds, got '{}'",
+                    raw
+                )
+            }),
+        Err(_) => Ok(Duration::seconds(DEFAULT_TTL_SECS)),
+    }
+}
+
+// Only alongside a token store that outlives the process; this also covers
+// `--no-persist`, which forces the memory store.
+fn enabled() -> Result<bool> {
+    Ok(token_store::active_store()?.is_persistent())
+}
+
+fn cache_path() -> Result<PathBuf> {
+    Ok(token_store::auth_dir()?.join(VALIDATION_CACHE_FILE))
+}
+
+// Only a hash of the token is written, never the token itself
+fn token_hash(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(&Sha256::digest(token.as_bytes())[..16])
+}
+
+fn load(path: &Path) -> Entries {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn recent_in(path: &Path, token: &str, ttl: Duration, now: DateTime<Utc>) -> Option<String> {
+    load(path)
+        .remove(&token_hash(token))
+        .filter(|entry| entry.validated_at <= now && now - entry.validated_at < ttl)
+        .map(|entry| entry.login)
+}
+
+fn update(change: impl FnOnce(&mut Entries, DateTime<Utc>)) -> Result<()> {
+    if !enabled()? {
+        return Ok(());
+    }
+    update_at(&cache_path()?, ttl()?, Utc::now(), change)
+}
+
+fn update_at(
+    path: &Path,
+    ttl: Duration,
+    now: DateTime<Utc>,
+    change: impl FnOnce(&mut Entries, DateTime<Utc>),
+) -> Result<()> {
+    let mut entries = load(path);
+    // Entries past the TTL are never used again
+    entries.retain(|_, entry| now - entry.validated_at < ttl);
+    change(&mut entries, now);
+    write_atomic(path, serde_json::to_string(&entries)?.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_store_writes_no_cache() {
+        let home = tempfile::tempdir().unwrap();
+        temp_env::with_vars(
+            [
+                ("HOME", Some(home.path().to_str().unwrap())),
+                ("XDG_CONFIG_HOME", None),
+                ("GOOSE_TOKEN_STORE", Some("memory")),
+                ("GOOSE_AUTH_VALIDATE_TTL", None),
+            ],
+            || {
+                record("gho_token", "octocat");
+                assert_eq!(recent_login("gho_token").unwrap(), None);
+                assert!(!cache_path().unwrap().exists());
+            },
+        );
+    }
+
+    #[test]
+    fn test_validation_is_trusted_within_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(VALIDATION_CACHE_FILE);
+        let ttl = Duration::seconds(300);
+        let now = Utc::now();
+        let remember = |entries: &mut Entries, now| {
+            entries.insert(
+                token_hash("gho_token"),
+                Entry {
+                    login: "octocat".to_string(),
+                    validated_at: now,
+                },
+            );
+        };
+        update_at(&path, ttl, now, remember).unwrap();
+        assert!(!std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("gho_token"));
+
+        let later = now + Duration::seconds(299);
+        assert_eq!(
+            recent_in(&path, "gho_token", ttl, later).as_deref(),
+            Some("octocat")
+        );
+        assert_eq!(recent_in(&path, "gho_other", ttl, later), None);
+        assert_eq!(
+            recent_in(&path, "gho_token", ttl, now + Duration::seconds(300)),
+            None
+        );
+
+        update_at(&path, ttl, later, |entries, _| {
+            entries.remove(&token_hash("gho_token"));
+        })
+        .unwrap();
+        assert_eq!(recent_in(&path, "gho_token", ttl, later), None);
+    }
+}
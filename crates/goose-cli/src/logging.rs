@@ -51,10 +51,17 @@ fn get_log_directory_with_date(test_date: Option<String>) -> Result<PathBuf> {
     Ok(log_dir)
 }
 
+/// Console verbosity: `GOOSE_LOG` takes `RUST_LOG`-style directives, e.g.
+/// `GOOSE_LOG=goose_cli::commands::auth=debug` to trace a login. Without it
+/// only warnings reach the terminal; `RUST_LOG` still controls the log file.
+fn console_filter() -> EnvFilter {
+    EnvFilter::try_from_env("GOOSE_LOG").unwrap_or_else(|_| EnvFilter::new("warn"))
+}
+
 /// Sets up the logging infrastructure for the application.
 /// This includes:
 /// - File-based logging with JSON formatting (DEBUG level)
-/// - Console output for development (INFO level)
+/// - Console output for development (WARN level, or as set by `GOOSE_LOG`)
 /// - Optional Langfuse integration (DEBUG level)
 /// - Optional error capture layer for benchmarking
 pub fn setup_logging(
@@ -134,7 +141,7 @@ fn setup_logging_internal(
             // Start building the subscriber
             let mut layers = vec![
                 file_layer.with_filter(env_filter).boxed(),
-                console_layer.with_filter(LevelFilter::WARN).boxed(),
+                console_layer.with_filter(console_filter()).boxed(),
             ];
 
             // Only add ErrorCaptureLayer if not in test mode
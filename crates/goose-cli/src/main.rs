@@ -1,5 +1,6 @@
 use anyhow::Result;
 use goose_cli::cli::cli;
+use goose_cli::commands::auth;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -34,5 +35,11 @@ async fn main() -> Result<()> {
         goose::tracing::shutdown_otlp();
     }
 
+    // Auth failures get their own exit status so scripts can tell them apart,
+    // and no error message may carry a code or token into shared logs
+    if let Err(e) = &result {
+        eprintln!("Error: {}", auth::redact(&format!("{:?}", e)));
+        std::process::exit(auth::exit_code(e));
+    }
     result
 }
@@ -0,0 +1,296 @@
+//! End-to-end runs of `goose auth login` against a fake OAuth provider.
+//!
+//! Each test starts a wiremock server with authorize, token and user
+//! endpoints, runs the real binary with the `custom` provider pointed at it
+//! and plays the browser: it reads the authorize URL from stderr and calls
+//! the callback server the way the provider's redirect would. The binary gets
+//! a cleared environment, so the tests cannot disturb each other.
+#![cfg(unix)]
+
+use serde_json::Value;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use url::Url;
+use wiremock::matchers::{body_string_contains, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const ACCESS_TOKEN: &str = "gho_integration";
+const CODE: &str = "good-code";
+
+/// A running `goose auth login` and the authorize URL it printed.
+struct Login {
+    child: Child,
+    authorize_url: Url,
+}
+
+impl Login {
+    async fn start(server: &MockServer, home: &Path, timeout_secs: u64) -> Self {
+        Self::start_with(server, home, timeout_secs, &[], "memory").await
+    }
+
+    /// `start` with extra `login` arguments and a `GOOSE_TOKEN_STORE` backend.
+    async fn start_with(
+        server: &MockServer,
+        home: &Path,
+        timeout_secs: u64,
+        extra_args: &[&str],
+        token_store: &str,
+    ) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_goose"))
+            .args(["auth", "--output", "json", "login", "--provider", "custom"])
+            .args(["--no-browser", "--timeout-action", "fail"])
+            .args(["--timeout", &timeout_secs.to_string()])
+            .args(extra_args)
+            .env_clear()
+            .env("PATH", std::env::var("PATH").unwrap_or_default())
+            .env("HOME", home)
+            .env("GOOSE_GITHUB_CLIENT_ID", "client")
+            .env(
+                "GOOSE_AUTH_REDIRECT_URL",
+                "http://127.0.0.1:8080/oauth_callback",
+            )
+            .env("GOOSE_AUTH_LISTEN_ADDR", "127.0.0.1:0")
+            .env(
+                "GOOSE_AUTH_AUTHORIZE_URL",
+                format!("{}/authorize", server.uri()),
+            )
+            .env("GOOSE_AUTH_TOKEN_URL", format!("{}/token", server.uri()))
+            .env("GOOSE_AUTH_IDENTITY_URL", format!("{}/user", server.uri()))
+            .env("GOOSE_AUTH_DEFAULT_SCOPES", "read:user")
+            .env("GOOSE_AUTH_MAX_RETRIES", "0")
+            .env("GOOSE_TOKEN_STORE", token_store)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("failed to start goose");
+
+        let prefix = format!("{}/authorize", server.uri());
+        let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+        let authorize_url = tokio::time::timeout(Duration::from_secs(30), async {
+            while let Some(line) = stderr.next_line().await.unwrap() {
+                if let Some(url) = line.trim().strip_prefix(&prefix) {
+                    return Url::parse(&format!("{}{}", prefix, url)).unwrap();
+                }
+            }
+            panic!("goose exited without printing the authorize URL");
+        })
+        .await
+        .expect("no authorize URL within 30s");
+        // Keep draining so the child never blocks on a full pipe
+        tokio::spawn(async move { while let Ok(Some(_)) = stderr.next_line().await {} });
+
+        Self {
+            child,
+            authorize_url,
+        }
+    }
+
+    fn param(&self, name: &str) -> String {
+        self.authorize_url
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap_or_else(|| panic!("authorize URL has no {}", name))
+    }
+
+    /// Play the provider's redirect: GET the callback with `query` appended.
+    async fn redirect(&self, query: &str) -> String {
+        let callback = Url::parse(&self.param("redirect_uri")).unwrap();
+        let mut stream = TcpStream::connect((
+            callback.host_str().unwrap(),
+            callback.port_or_known_default().unwrap(),
+        ))
+        .await
+        .unwrap();
+        let request = format!(
+            "GET {}?{} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            callback.path(),
+            query,
+            callback.host_str().unwrap()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    /// Exit status and the JSON object printed to stdout.
+    async fn finish(mut self) -> (bool, Value) {
+        let mut stdout = String::new();
+        self.child
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut stdout)
+            .await
+            .unwrap();
+        let status = tokio::time::timeout(Duration::from_secs(30), self.child.wait())
+            .await
+            .expect("goose did not exit")
+            .unwrap();
+        let json = serde_json::from_str(stdout.trim())
+            .unwrap_or_else(|e| panic!("stdout is not JSON ({}): {}", e, stdout));
+        (status.success(), json)
+    }
+}
+
+/// Every file below `dir`, recursively.
+fn files_under(dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(files_under(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+async fn mount_token_endpoint(server: &MockServer, expected_calls: u64) {
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains(format!("code={}", CODE)))
+        .and(body_string_contains("code_verifier="))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": ACCESS_TOKEN,
+            "token_type": "bearer",
+            "scope": "read:user"
+        })))
+        .expect(expected_calls)
+        .mount(server)
+        .await;
+}
+
+async fn mount_user_endpoint(server: &MockServer, status: u16) {
+    Mock::given(method("GET"))
+        .and(path("/user"))
+        .and(header(
+            "authorization",
+            format!("Bearer {}", ACCESS_TOKEN).as_str(),
+        ))
+        .respond_with(
+            ResponseTemplate::new(status).set_body_json(serde_json::json!({"login": "octocat"})),
+        )
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_login_round_trip() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, 1).await;
+    mount_user_endpoint(&server, 200).await;
+    let home = tempfile::tempdir().unwrap();
+
+    let login = Login::start(&server, home.path(), 30).await;
+    assert_eq!(login.param("client_id"), "client");
+    assert_eq!(login.param("code_challenge_method"), "S256");
+    let state = login.param("state");
+    let page = login
+        .redirect(&format!("code={}&state={}", CODE, state))
+        .await;
+    assert!(page.contains("Authentication succeeded"), "{}", page);
+
+    let (success, json) = login.finish().await;
+    assert!(success, "{}", json);
+    assert_eq!(json["authenticated"], true);
+    assert_eq!(json["provider"], "custom");
+    assert_eq!(json["login"], "octocat");
+    assert_eq!(json["scopes"], serde_json::json!(["read:user"]));
+}
+
+#[tokio::test]
+async fn test_mismatched_state_is_rejected() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, 0).await;
+    let home = tempfile::tempdir().unwrap();
+
+    let login = Login::start(&server, home.path(), 2).await;
+    let page = login
+        .redirect(&format!("code={}&state=forged-state", CODE))
+        .await;
+    assert!(page.contains("Invalid state parameter"), "{}", page);
+
+    let (success, json) = login.finish().await;
+    assert!(!success);
+    assert!(
+        json["error"].as_str().unwrap().contains("Timed out"),
+        "{}",
+        json
+    );
+}
+
+#[tokio::test]
+async fn test_callback_without_code_is_ignored() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, 0).await;
+    let home = tempfile::tempdir().unwrap();
+
+    let login = Login::start(&server, home.path(), 2).await;
+    let state = login.param("state");
+    let page = login.redirect(&format!("state={}", state)).await;
+    assert!(page.starts_with("HTTP/1.1 400"), "{}", page);
+
+    let (success, json) = login.finish().await;
+    assert!(!success);
+    assert!(
+        json["error"].as_str().unwrap().contains("Timed out"),
+        "{}",
+        json
+    );
+}
+
+#[tokio::test]
+async fn test_token_rejected_by_userinfo_fails_login() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, 1).await;
+    mount_user_endpoint(&server, 401).await;
+    let home = tempfile::tempdir().unwrap();
+
+    let login = Login::start(&server, home.path(), 30).await;
+    let state = login.param("state");
+    login
+        .redirect(&format!("code={}&state={}", CODE, state))
+        .await;
+
+    let (success, json) = login.finish().await;
+    assert!(!success);
+    assert_eq!(
+        json["error"],
+        "The identity endpoint rejected the new token"
+    );
+}
+
+#[tokio::test]
+async fn test_no_persist_login_writes_nothing() {
+    let server = MockServer::start().await;
+    mount_token_endpoint(&server, 1).await;
+    mount_user_endpoint(&server, 200).await;
+    let home = tempfile::tempdir().unwrap();
+
+    // The file store would write below HOME if --no-persist did not win
+    let login = Login::start_with(&server, home.path(), 30, &["--no-persist"], "file").await;
+    let state = login.param("state");
+    login
+        .redirect(&format!("code={}&state={}", CODE, state))
+        .await;
+
+    let (success, json) = login.finish().await;
+    assert!(success, "{}", json);
+    assert_eq!(json["login"], "octocat");
+    // goose's own log files are the only thing allowed under HOME, wherever
+    // the platform puts the auth dir
+    let written: Vec<_> = files_under(home.path())
+        .into_iter()
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) != Some("log"))
+        .collect();
+    assert_eq!(written, Vec::<std::path::PathBuf>::new());
+}